@@ -0,0 +1,60 @@
+use congkit::{CongkitDB, CongkitFilter, CongkitVersion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use regex::Regex;
+use std::{fs, hint::black_box};
+
+/// The pre-index lookup: compile a regex per query and scan every code.
+struct ScanDB {
+    codes: Vec<(String, char, i32)>,
+}
+
+impl ScanDB {
+    fn from_txt(txt: &str) -> Self {
+        let codes = txt
+            .lines()
+            .filter(|line| !(line.starts_with("# ") || line.is_empty()))
+            .flat_map(|line| {
+                let fields = line.split(' ').collect::<Vec<&str>>();
+                let character = fields[0].chars().next().unwrap();
+                let order = fields[14].parse::<i32>().unwrap();
+                fields[11]
+                    .split(',')
+                    .filter(|code| *code != "NA")
+                    .map(move |code| (code.to_string(), character, order))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Self { codes }
+    }
+
+    fn get_characters(&self, code: &str) -> Vec<char> {
+        let re = Regex::new(&format!("^{}$", code.replace('*', ".*").replace('?', "."))).unwrap();
+        let mut filt = self
+            .codes
+            .iter()
+            .filter(|(code, _, _)| re.is_match(code))
+            .collect::<Vec<_>>();
+        filt.sort_by_key(|(_, c, order)| (*order, *c));
+        filt.iter().map(|(_, c, _)| *c).collect()
+    }
+}
+
+fn lookup(c: &mut Criterion) {
+    let txt = fs::read_to_string("data/table.txt").unwrap();
+    let db = CongkitDB::from_txt(&txt, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+    let scan = ScanDB::from_txt(&txt);
+
+    let mut group = c.benchmark_group("get_characters");
+    for query in ["hqi", "hq*", "*hqi", "h*i", "?qi"] {
+        group.bench_with_input(BenchmarkId::new("index", query), query, |b, q| {
+            b.iter(|| db.get_characters(black_box(q)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("regex_scan", query), query, |b, q| {
+            b.iter(|| scan.get_characters(black_box(q)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
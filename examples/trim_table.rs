@@ -1,25 +1,28 @@
 use anyhow::Result;
-use congkit::{CongkitDB, CongkitFilter};
-use std::{fs, io::Write};
+use congkit::{CongkitDB, CongkitFilter, DataWriter};
+use std::fs;
 
 fn main() -> Result<()> {
     let txt = fs::read_to_string("data/table.txt")?;
-    let entries = CongkitDB::to_entries(&txt, &CongkitFilter::all());
+    let filter = CongkitFilter::all();
+    let entries = CongkitDB::to_entries(&txt, &filter)?;
     println!("{}", entries.len());
-    let mut full = fs::File::create("data/full_table.dat")?;
-    full.write_all(&bitcode::encode(&entries))?;
-    let trimmed = CongkitDB::to_entries(
-        &txt,
-        &CongkitFilter {
-            chinese: false,
-            big5: true,
-            hkscs: true,
-            taiwanese: false,
-            ..Default::default()
-        },
-    );
+    fs::write(
+        "data/full_table.dat",
+        DataWriter::new(&txt, filter).write(&entries),
+    )?;
+    let filter = CongkitFilter {
+        chinese: false,
+        big5: true,
+        hkscs: true,
+        taiwanese: false,
+        ..Default::default()
+    };
+    let trimmed = CongkitDB::to_entries(&txt, &filter)?;
     println!("{}", trimmed.len());
-    let mut trim = fs::File::create("data/trimmed_table.dat")?;
-    trim.write_all(&bitcode::encode(&trimmed))?;
+    fs::write(
+        "data/trimmed_table.dat",
+        DataWriter::new(&txt, filter).write(&trimmed),
+    )?;
     Ok(())
 }
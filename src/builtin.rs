@@ -0,0 +1,54 @@
+//! Tables compiled into the crate, so applications need no `data/` directory
+//! at runtime. Each is behind its own cargo feature:
+//!
+//! - `embed-full`: every character in `table.txt`.
+//! - `embed-trimmed`: only the Big5 and HKSCS characters, a fraction of the
+//!   size.
+
+use crate::{CongkitDB, CongkitFilter, CongkitVersion, DataError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinTable {
+    #[cfg(feature = "embed-full")]
+    Full,
+    #[cfg(feature = "embed-trimmed")]
+    Trimmed,
+}
+
+impl BuiltinTable {
+    pub fn data(self) -> &'static [u8] {
+        match self {
+            #[cfg(feature = "embed-full")]
+            Self::Full => include_bytes!("../data/full_table.dat"),
+            #[cfg(feature = "embed-trimmed")]
+            Self::Trimmed => include_bytes!("../data/trimmed_table.dat"),
+        }
+    }
+}
+
+impl Default for BuiltinTable {
+    /// The most complete table compiled in.
+    fn default() -> Self {
+        #[cfg(feature = "embed-full")]
+        return Self::Full;
+        #[cfg(not(feature = "embed-full"))]
+        return Self::Trimmed;
+    }
+}
+
+impl CongkitDB {
+    /// Loads the most complete table compiled in. `filter` is applied on top
+    /// of the table's own contents, so the trimmed table never yields more
+    /// than its Big5 and HKSCS characters.
+    pub fn new(version: CongkitVersion, filter: CongkitFilter) -> Result<Self, DataError> {
+        Self::builtin(BuiltinTable::default(), version, filter)
+    }
+
+    pub fn builtin(
+        table: BuiltinTable,
+        version: CongkitVersion,
+        filter: CongkitFilter,
+    ) -> Result<Self, DataError> {
+        Self::from_data(table.data(), version, filter)
+    }
+}
@@ -0,0 +1,250 @@
+//! Cangjie codes packed into a `u32`.
+
+use crate::{CangjieKey, CodeError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const KEY_BITS: usize = 5;
+const KEY_MASK: u32 = (1 << KEY_BITS) - 1;
+
+/// A Cangjie code of 1 to [`MAX_LEN`](Self::MAX_LEN) keys `a`–`z`.
+///
+/// Each key takes 5 bits, 1 for `a` to 26 for `z`, with the first key in the
+/// highest slot and unused slots 0, so comparing the packed values orders
+/// codes the same way as comparing their strings.
+///
+/// Displays as its keys (`hqi`), or as radicals with `{:#}` (`竹手戈`).
+/// Serializes as its keys, and decodes from bitcode only if the packed
+/// value is valid.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CangjieCode(u32);
+
+impl CangjieCode {
+    pub const MAX_LEN: usize = 5;
+
+    /// No keys; only used as the prefix of every code.
+    pub(crate) const EMPTY: Self = Self(0);
+
+    fn shift(position: usize) -> usize {
+        (Self::MAX_LEN - 1 - position) * KEY_BITS
+    }
+
+    fn slot(&self, position: usize) -> u32 {
+        (self.0 >> Self::shift(position)) & KEY_MASK
+    }
+
+    pub fn new(keys: &str) -> Result<Self, CodeError> {
+        if keys.is_empty() {
+            return Err(CodeError::Empty);
+        }
+        let len = keys.chars().count();
+        if len > Self::MAX_LEN {
+            return Err(CodeError::TooLong(len));
+        }
+        keys.chars().try_fold(Self::EMPTY, |code, key| {
+            code.push(key).ok_or(CodeError::InvalidKey(key))
+        })
+    }
+
+    /// The packed value, for storing codes compactly.
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Unpacks a value from [`to_u32`](Self::to_u32), if it is a valid code.
+    pub fn from_u32(value: u32) -> Option<Self> {
+        let code = Self(value);
+        let valid = value >> (Self::MAX_LEN * KEY_BITS) == 0
+            && !code.is_empty()
+            && (0..Self::MAX_LEN).all(|i| {
+                let slot = code.slot(i);
+                // Keys are contiguous from the first slot.
+                slot <= 26 && (slot != 0 || (i..Self::MAX_LEN).all(|j| code.slot(j) == 0))
+            });
+        valid.then_some(code)
+    }
+
+    pub fn len(&self) -> usize {
+        (0..Self::MAX_LEN)
+            .take_while(|i| self.slot(*i) != 0)
+            .count()
+    }
+
+    /// Whether there are no keys, which only the internal prefix of every
+    /// code can be.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn keys(self) -> impl Iterator<Item = CangjieKey> {
+        (0..self.len()).map_while(move |i| CangjieKey::ALL.get(self.slot(i) as usize - 1).copied())
+    }
+
+    /// The letters of the keys, e.g. `h`, `q`, `i`.
+    pub fn letters(self) -> impl Iterator<Item = char> {
+        self.keys().map(CangjieKey::letter)
+    }
+
+    pub fn first(&self) -> Option<CangjieKey> {
+        self.keys().next()
+    }
+
+    pub fn last(&self) -> Option<CangjieKey> {
+        self.keys().last()
+    }
+
+    /// The radical named by each key, e.g. `竹`, `手`, `戈`.
+    pub fn radicals(self) -> impl Iterator<Item = char> {
+        self.keys().map(CangjieKey::radical)
+    }
+
+    pub fn starts_with(&self, prefix: &Self) -> bool {
+        let len = prefix.len();
+        len == 0 || self.0 >> Self::shift(len - 1) == prefix.0 >> Self::shift(len - 1)
+    }
+
+    /// Appends the key typed with `letter`, or returns `None` if it is not
+    /// `a`–`z` or the code is full.
+    pub fn push(self, letter: char) -> Option<Self> {
+        self.push_key(CangjieKey::from_letter(letter)?)
+    }
+
+    /// Appends `key`, or returns `None` if the code is full.
+    pub fn push_key(self, key: CangjieKey) -> Option<Self> {
+        let len = self.len();
+        if len == Self::MAX_LEN {
+            return None;
+        }
+        let value = key as u32 + 1;
+        Some(Self(self.0 | value << Self::shift(len)))
+    }
+
+    /// Removes the last key.
+    pub fn pop(self) -> Option<(Self, char)> {
+        let len = self.len();
+        let last = self.letters().last()?;
+        Some((Self(self.0 & !(KEY_MASK << Self::shift(len - 1))), last))
+    }
+
+    /// The keys in reverse order, so that suffixes can be looked up as
+    /// prefixes.
+    pub(crate) fn reversed(self) -> Self {
+        let keys = self.keys().collect::<Vec<CangjieKey>>();
+        keys.into_iter()
+            .rev()
+            .fold(Self::EMPTY, |code, key| code.push_key(key).unwrap())
+    }
+}
+
+impl fmt::Display for CangjieCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.radicals()
+                .try_for_each(|radical| write!(f, "{radical}"))
+        } else {
+            self.letters().try_for_each(|key| write!(f, "{key}"))
+        }
+    }
+}
+
+impl fmt::Debug for CangjieCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl FromStr for CangjieCode {
+    type Err = CodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl PartialEq<str> for CangjieCode {
+    fn eq(&self, other: &str) -> bool {
+        self.letters().eq(other.chars())
+    }
+}
+
+impl PartialEq<&str> for CangjieCode {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl Serialize for CangjieCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CangjieCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let keys = String::deserialize(deserializer)?;
+        keys.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(keys: &str) -> CangjieCode {
+        keys.parse().unwrap()
+    }
+
+    #[test]
+    fn packs_and_unpacks() {
+        for keys in ["a", "z", "hqi", "abcde", "zzzzz"] {
+            let packed = code(keys).to_u32();
+            assert_eq!(CangjieCode::from_u32(packed), Some(code(keys)));
+            assert_eq!(CangjieCode::from_u32(packed).unwrap().to_string(), keys);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_packed_values() {
+        let slot = |position: usize, value: u32| value << CangjieCode::shift(position);
+        // No keys.
+        assert_eq!(CangjieCode::from_u32(0), None);
+        // A gap before the second key.
+        assert_eq!(CangjieCode::from_u32(slot(0, 1) | slot(2, 1)), None);
+        // No key 27.
+        assert_eq!(CangjieCode::from_u32(slot(0, 27)), None);
+        // Bits above the fifth slot.
+        assert_eq!(CangjieCode::from_u32(code("a").to_u32() | 1 << 31), None);
+    }
+
+    #[test]
+    fn orders_like_strings() {
+        let mut keys = vec!["z", "ab", "a", "yz", "abc", "aaaaa", "b"];
+        let mut codes = keys.iter().map(|keys| code(keys)).collect::<Vec<_>>();
+        keys.sort();
+        codes.sort();
+        assert_eq!(
+            codes.iter().map(CangjieCode::to_string).collect::<Vec<_>>(),
+            keys
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(CangjieCode::new(""), Err(CodeError::Empty));
+        assert_eq!(CangjieCode::new("abcdef"), Err(CodeError::TooLong(6)));
+        assert_eq!(CangjieCode::new("aB"), Err(CodeError::InvalidKey('B')));
+        assert_eq!(CangjieCode::new("a*"), Err(CodeError::InvalidKey('*')));
+    }
+
+    #[test]
+    fn keys_and_radicals() {
+        assert_eq!(code("hqi").first(), Some(CangjieKey::H));
+        assert_eq!(code("hqi").last(), Some(CangjieKey::I));
+        assert_eq!(format!("{:#}", code("hqi")), "竹手戈");
+        assert_eq!(code("hqi").reversed(), code("iqh"));
+        assert_eq!(code("hqi").pop(), Some((code("hq"), 'i')));
+        assert!(code("hqi").starts_with(&code("hq")));
+        assert!(!code("hqi").starts_with(&code("hi")));
+    }
+}
@@ -0,0 +1,194 @@
+//! Keystroke-by-keystroke prefix completion.
+
+use crate::index::CodeIndex;
+use crate::{CangjieCode, CongkitDB, PatternError};
+use std::ops::Range;
+
+/// Candidates for a key buffer: characters whose code is exactly the buffer,
+/// then characters whose code merely starts with it. Each list is ranked by
+/// `order`, and a character appears at most once across both.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completion {
+    pub exact: Vec<char>,
+    pub completions: Vec<char>,
+}
+
+impl Completion {
+    /// All candidates, exact matches first.
+    pub fn iter(&self) -> impl Iterator<Item = &char> {
+        self.exact.iter().chain(self.completions.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.exact.len() + self.completions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.completions.is_empty()
+    }
+
+    /// Keeps the first `limit` candidates, exact matches first.
+    pub fn truncate(&mut self, limit: usize) {
+        self.exact.truncate(limit);
+        self.completions.truncate(limit - self.exact.len());
+    }
+}
+
+/// A key buffer together with the index range of codes it prefixes, so that
+/// typing one more key narrows the previous range instead of searching the
+/// whole index again.
+#[derive(Debug, Clone)]
+pub struct PrefixCursor<'a> {
+    index: &'a CodeIndex,
+    buffer: String,
+    /// `buffer` packed, for comparing against the index.
+    code: CangjieCode,
+    range: Range<usize>,
+}
+
+impl<'a> PrefixCursor<'a> {
+    pub(crate) fn new(index: &'a CodeIndex, buffer: &str) -> Result<Self, PatternError> {
+        let mut cursor = Self {
+            index,
+            buffer: String::new(),
+            code: CangjieCode::EMPTY,
+            range: 0..index.entries().len(),
+        };
+        for (position, key) in buffer.chars().enumerate() {
+            if !key.is_ascii_lowercase() {
+                return Err(PatternError::InvalidCharacter {
+                    position,
+                    character: key,
+                });
+            }
+            if !cursor.push(key) {
+                return Err(PatternError::TooLong {
+                    min_len: buffer.chars().count(),
+                    max_len: CongkitDB::MAX_CODE_LEN,
+                });
+            }
+        }
+        Ok(cursor)
+    }
+
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Appends `key` and narrows the candidates. Returns `false`, leaving the
+    /// cursor unchanged, if `key` is not a Cangjie key or the buffer is full.
+    pub fn push(&mut self, key: char) -> bool {
+        let Some(code) = self.code.push(key) else {
+            return false;
+        };
+        self.buffer.push(key);
+        self.code = code;
+        self.range = self.index.narrow(self.range.clone(), &self.code);
+        true
+    }
+
+    /// Removes the last key, widening the candidates again.
+    pub fn pop(&mut self) -> Option<char> {
+        let (code, key) = self.code.pop()?;
+        self.buffer.pop();
+        self.code = code;
+        self.range = self.index.prefix_range(&self.code);
+        Some(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.code = CangjieCode::EMPTY;
+        self.range = 0..self.index.entries().len();
+    }
+
+    /// Whether any code starts with the buffer.
+    pub fn has_matches(&self) -> bool {
+        !self.range.is_empty()
+    }
+
+    /// Up to `limit` candidates, exact matches first.
+    pub fn candidates(&self, limit: usize) -> Completion {
+        let hits = &self.index.entries()[self.range.clone()];
+        // Codes are sorted, so the exact matches come first in the range.
+        let split = hits.partition_point(|entry| entry.code == self.code);
+        let exact = CodeIndex::sorted_characters(hits[..split].iter());
+        let mut completion = Completion {
+            completions: match exact.len() < limit {
+                true => CodeIndex::sorted_characters(
+                    hits[split..]
+                        .iter()
+                        .filter(|entry| !exact.contains(&entry.character)),
+                ),
+                false => Vec::new(),
+            },
+            exact,
+        };
+        completion.truncate(limit);
+        completion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Completion, CongkitDB, CongkitFilter, CongkitVersion};
+
+    const TXT: &str = "日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a a NA 90\n\
+                       昌 昌 1 1 0 0 1 0 0 0 0 aa aa NA 80\n\
+                       明 明 1 1 0 0 1 0 0 0 0 ab ab NA 70\n\
+                       旦 旦 1 1 0 0 1 0 0 0 0 am am NA 60\n\
+                       月 月 1 1 0 0 1 0 0 0 0 b b NA 50\n";
+
+    fn db() -> CongkitDB {
+        CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap()
+    }
+
+    #[test]
+    fn push_and_pop_match_a_fresh_cursor() {
+        let db = db();
+        let mut cursor = db.cursor("").unwrap();
+        for keys in ["a", "ab"] {
+            assert!(cursor.push(keys.chars().last().unwrap()));
+            let fresh = db.cursor(keys).unwrap();
+            assert_eq!(cursor.buffer(), keys);
+            assert_eq!(cursor.range, fresh.range);
+            assert_eq!(cursor.candidates(10), fresh.candidates(10));
+        }
+        assert_eq!(cursor.pop(), Some('b'));
+        assert_eq!(cursor.range, db.cursor("a").unwrap().range);
+        assert!(cursor.push('m'));
+        assert_eq!(cursor.range, db.cursor("am").unwrap().range);
+        assert_eq!(cursor.candidates(10).exact, ['旦']);
+        assert!(!cursor.push('*'));
+        assert_eq!(cursor.buffer(), "am");
+        assert!(cursor.push('z'));
+        assert!(!cursor.has_matches());
+    }
+
+    #[test]
+    fn exact_matches_come_first() {
+        let completion = db().cursor("a").unwrap().candidates(10);
+        assert_eq!(completion.exact, ['日', '曰']);
+        assert_eq!(completion.completions, ['昌', '明', '旦']);
+        assert_eq!(
+            completion.iter().copied().collect::<Vec<char>>(),
+            ['日', '曰', '昌', '明', '旦']
+        );
+
+        let mut truncated = completion;
+        truncated.truncate(3);
+        assert_eq!(truncated.exact, ['日', '曰']);
+        assert_eq!(truncated.completions, ['昌']);
+        assert_eq!(db().cursor("a").unwrap().candidates(3), truncated);
+        truncated.truncate(1);
+        assert_eq!(truncated.exact, ['日']);
+        assert!(truncated.completions.is_empty());
+    }
+
+    #[test]
+    fn empty_buffer_has_no_candidates() {
+        assert_eq!(db().complete("", 5).unwrap(), Completion::default());
+        assert_eq!(db().complete("a", 5).unwrap().len(), 5);
+    }
+}
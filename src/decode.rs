@@ -0,0 +1,82 @@
+//! Decoding space-separated code sequences back into text.
+
+use crate::CongkitDB;
+use serde::{Deserialize, Serialize};
+
+/// One code of a decoded sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DecodedPosition {
+    pub code: String,
+    /// Every matching character, best first. Empty if the code is invalid or
+    /// matches nothing.
+    pub candidates: Vec<char>,
+    /// Whether more than one character matches.
+    pub ambiguous: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Decoded {
+    /// The top candidate for each code. Codes without candidates are kept
+    /// as they were written.
+    pub text: String,
+    pub positions: Vec<DecodedPosition>,
+}
+
+impl CongkitDB {
+    /// Decodes whitespace-separated codes (e.g. `hqi rgpd gi`) in the active
+    /// version. Each code may also be a wildcard pattern.
+    pub fn decode(&self, codes: &str) -> Decoded {
+        let mut decoded = Decoded::default();
+        for code in codes.split_whitespace() {
+            let candidates = self.get_characters(code).unwrap_or_default();
+            match candidates.first() {
+                Some(c) => decoded.text.push(*c),
+                None => decoded.text.push_str(code),
+            }
+            decoded.positions.push(DecodedPosition {
+                code: code.to_string(),
+                ambiguous: candidates.len() > 1,
+                candidates,
+            });
+        }
+        decoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CongkitFilter, CongkitVersion};
+
+    const TXT: &str = "我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA 22308\n\
+                       日 日 1 1 0 0 1 0 0 0 0 a a NA 23059\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a a NA 23060\n";
+
+    #[test]
+    fn decodes_each_code() {
+        let db = CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        let decoded = db.decode(" hqi  a zz hQi h*\n");
+        assert_eq!(decoded.text, "我曰zzhQi我");
+        assert_eq!(
+            decoded.positions[..2],
+            [
+                DecodedPosition {
+                    code: "hqi".to_string(),
+                    candidates: vec!['我'],
+                    ambiguous: false,
+                },
+                DecodedPosition {
+                    code: "a".to_string(),
+                    candidates: vec!['曰', '日'],
+                    ambiguous: true,
+                },
+            ]
+        );
+        // Unknown and invalid codes have no candidates and are kept as text.
+        for position in &decoded.positions[2..4] {
+            assert!(position.candidates.is_empty() && !position.ambiguous);
+        }
+        assert_eq!(decoded.positions[4].candidates, ['我']);
+        assert_eq!(db.decode(""), Decoded::default());
+    }
+}
@@ -0,0 +1,151 @@
+//! Differences between the V3 and V5 codes of a loaded table.
+
+use crate::{CangjieCode, CongkitDB, CongkitVersion};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A character's codes in each version.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CodeChange {
+    pub character: char,
+    pub v3: Vec<CangjieCode>,
+    pub v5: Vec<CangjieCode>,
+}
+
+/// Characters sharing a code.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Collision {
+    pub code: CangjieCode,
+    pub characters: Vec<char>,
+}
+
+/// Every character whose codes differ between V3 and V5, grouped by the kind
+/// of change, sorted by character.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VersionDiff {
+    /// Characters with V5 codes but no V3 code.
+    pub added: Vec<CodeChange>,
+    /// Characters with V3 codes but no V5 code.
+    pub removed: Vec<CodeChange>,
+    /// Characters with codes in both versions, but not the same ones.
+    pub changed: Vec<CodeChange>,
+    /// V5 codes shared by at least two characters that share no V3 code.
+    pub v5_collisions: Vec<Collision>,
+    /// V3 codes shared by at least two characters that share no V5 code.
+    pub v3_collisions: Vec<Collision>,
+}
+
+impl CongkitDB {
+    /// Compares the V3 and V5 codes of every loaded character.
+    pub fn diff_versions(&self) -> VersionDiff {
+        let mut diff = VersionDiff::default();
+        let mut characters = self.entries.keys().copied().collect::<Vec<char>>();
+        characters.sort();
+        for character in characters {
+            let v3 = self
+                .get_code_in(&character, CongkitVersion::V3)
+                .unwrap_or_default();
+            let v5 = self
+                .get_code_in(&character, CongkitVersion::V5)
+                .unwrap_or_default();
+            let same = v3.len() == v5.len() && v3.iter().all(|code| v5.contains(code));
+            let list = match (v3.is_empty(), v5.is_empty()) {
+                _ if same => continue,
+                (true, false) => &mut diff.added,
+                (false, true) => &mut diff.removed,
+                _ => &mut diff.changed,
+            };
+            list.push(CodeChange { character, v3, v5 });
+        }
+        diff.v5_collisions = self.new_collisions(CongkitVersion::V5, CongkitVersion::V3);
+        diff.v3_collisions = self.new_collisions(CongkitVersion::V3, CongkitVersion::V5);
+        diff
+    }
+
+    /// Codes of `version` whose characters include a pair sharing no code in
+    /// `other`.
+    fn new_collisions(&self, version: CongkitVersion, other: CongkitVersion) -> Vec<Collision> {
+        let mut groups: Vec<(CangjieCode, Vec<char>)> = Vec::new();
+        for (code, character) in self
+            .index(version)
+            .entries()
+            .iter()
+            .map(|e| (e.code, e.character))
+        {
+            match groups.last_mut() {
+                Some((last, chars)) if *last == code => {
+                    if !chars.contains(&character) {
+                        chars.push(character);
+                    }
+                }
+                _ => groups.push((code, vec![character])),
+            }
+        }
+        let mut other_codes: HashMap<char, HashSet<CangjieCode>> = HashMap::new();
+        let mut collisions = Vec::new();
+        for (code, mut characters) in groups.into_iter().filter(|(_, chars)| chars.len() > 1) {
+            for c in characters.iter() {
+                other_codes.entry(*c).or_insert_with(|| {
+                    self.get_code_in(c, other)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect()
+                });
+            }
+            let codes = characters
+                .iter()
+                .map(|c| &other_codes[c])
+                .collect::<Vec<&HashSet<CangjieCode>>>();
+            let is_new = (0..codes.len())
+                .any(|i| (i + 1..codes.len()).any(|j| codes[i].is_disjoint(codes[j])));
+            if is_new {
+                characters.sort();
+                collisions.push(Collision { code, characters });
+            }
+        }
+        collisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CongkitFilter;
+
+    /// 日 is unchanged; 曰 moves from `a` to `xa`, so `a` is a V3 collision
+    /// between characters with no V5 code in common. 甲 and 申 share `wl`
+    /// in both versions, which is not new.
+    const TXT: &str = "日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a xa NA 90\n\
+                       甲 甲 1 1 0 0 1 0 0 0 0 wl wl NA 80\n\
+                       申 申 1 1 0 0 1 0 0 0 0 wl wl NA 70\n\
+                       丁 丁 1 1 0 0 1 0 0 0 0 NA mn NA 60\n\
+                       乙 乙 1 1 0 0 1 0 0 0 0 nu NA NA 50\n";
+
+    fn change(character: char, v3: &[&str], v5: &[&str]) -> CodeChange {
+        let codes = |keys: &[&str]| keys.iter().map(|keys| keys.parse().unwrap()).collect();
+        CodeChange {
+            character,
+            v3: codes(v3),
+            v5: codes(v5),
+        }
+    }
+
+    #[test]
+    fn groups_changes_and_new_collisions() {
+        let db = CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(
+            db.diff_versions(),
+            VersionDiff {
+                added: vec![change('丁', &[], &["mn"])],
+                removed: vec![change('乙', &["nu"], &[])],
+                changed: vec![change('曰', &["a"], &["xa"])],
+                v5_collisions: Vec::new(),
+                v3_collisions: vec![Collision {
+                    code: "a".parse().unwrap(),
+                    characters: vec!['日', '曰'],
+                }],
+            }
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Encoding running text into Cangjie codes.
+
+use crate::{CangjieCode, CongkitDB};
+use serde::{Deserialize, Serialize};
+
+/// A piece of encoded text.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Segment {
+    /// A character with at least one code in the active version.
+    Char {
+        character: char,
+        codes: Vec<CangjieCode>,
+        radicals: Vec<String>,
+    },
+    /// A run of characters without codes (whitespace, Latin text, characters
+    /// missing from the table), passed through unchanged.
+    Text(String),
+}
+
+/// How [`CongkitDB::encode_to_string`] renders segments. Characters with
+/// several codes are rendered with their first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodeFormat {
+    /// Space-separated codes, e.g. `hqi bbpe onf`.
+    #[default]
+    Codes,
+    /// Space-separated radicals, e.g. `竹手戈 月月心水 人弓火`.
+    Radicals,
+    /// The original text with each character's code after it, e.g.
+    /// `我(hqi)愛(bbpe)你(onf)`.
+    Annotated,
+}
+
+impl CongkitDB {
+    /// Splits `text` into encodable characters and pass-through runs.
+    pub fn encode(&self, text: &str) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        for character in text.chars() {
+            match self.get_code(&character) {
+                Some(codes) if !codes.is_empty() => {
+                    let radicals = codes.iter().map(|code| format!("{code:#}")).collect();
+                    segments.push(Segment::Char {
+                        character,
+                        codes,
+                        radicals,
+                    });
+                }
+                _ => match segments.last_mut() {
+                    Some(Segment::Text(run)) => run.push(character),
+                    _ => segments.push(Segment::Text(character.to_string())),
+                },
+            }
+        }
+        segments
+    }
+
+    /// Encodes `text` and renders it in `format`. In the space-separated
+    /// formats, whitespace only separates tokens, and any other pass-through
+    /// run becomes a token of its own.
+    pub fn encode_to_string(&self, text: &str, format: EncodeFormat) -> String {
+        let segments = self.encode(text);
+        match format {
+            EncodeFormat::Codes | EncodeFormat::Radicals => segments
+                .iter()
+                .flat_map(|segment| match segment {
+                    Segment::Char {
+                        codes, radicals, ..
+                    } => {
+                        vec![match format {
+                            EncodeFormat::Radicals => radicals[0].clone(),
+                            _ => codes[0].to_string(),
+                        }]
+                    }
+                    Segment::Text(run) => run.split_whitespace().map(str::to_string).collect(),
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+            EncodeFormat::Annotated => segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Char {
+                        character, codes, ..
+                    } => format!("{}({})", character, codes[0]),
+                    Segment::Text(run) => run.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CongkitFilter, CongkitVersion};
+
+    const TXT: &str = "我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA 22308\n\
+                       愛 爱 1 1 0 0 1 0 0 0 0 bbpe bbpe NA 21720\n\
+                       你 你 1 1 0 0 1 0 0 0 0 onf onf NA 22461\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a,ax a,xa NA 23060\n";
+
+    fn db() -> CongkitDB {
+        CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap()
+    }
+
+    #[test]
+    fn merges_pass_through_runs() {
+        assert_eq!(
+            db().encode("我 is 你!"),
+            [
+                Segment::Char {
+                    character: '我',
+                    codes: vec!["hqi".parse().unwrap()],
+                    radicals: vec!["竹手戈".to_string()],
+                },
+                Segment::Text(" is ".to_string()),
+                Segment::Char {
+                    character: '你',
+                    codes: vec!["onf".parse().unwrap()],
+                    radicals: vec!["人弓火".to_string()],
+                },
+                Segment::Text("!".to_string()),
+            ]
+        );
+        assert!(db().encode("").is_empty());
+    }
+
+    #[test]
+    fn formats() {
+        let db = db();
+        let text = "我愛你, 曰 ok";
+        assert_eq!(
+            db.encode_to_string(text, EncodeFormat::Codes),
+            "hqi bbpe onf , a ok"
+        );
+        assert_eq!(
+            db.encode_to_string(text, EncodeFormat::Radicals),
+            "竹手戈 月月心水 人弓火 , 日 ok"
+        );
+        assert_eq!(
+            db.encode_to_string(text, EncodeFormat::Annotated),
+            "我(hqi)愛(bbpe)你(onf), 曰(a) ok"
+        );
+    }
+}
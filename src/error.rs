@@ -0,0 +1,100 @@
+use crate::CongkitVersion;
+use thiserror::Error;
+
+/// Why a line of `table.txt`, a user dictionary or a selection history could
+/// not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseErrorKind {
+    #[error("expected {expected} fields, found {found}")]
+    FieldCount { expected: usize, found: usize },
+    #[error("field is empty")]
+    EmptyField,
+    #[error("expected a single character, found {0:?}")]
+    NotACharacter(String),
+    #[error("expected 0 or 1, found {0:?}")]
+    NotABool(String),
+    #[error("invalid Cangjie code {0:?}")]
+    InvalidCode(String),
+    #[error("invalid order {0:?}")]
+    InvalidOrder(String),
+    #[error("unknown directive {0:?}")]
+    UnknownDirective(String),
+    #[error("expected a clock line")]
+    MissingClock,
+    #[error("invalid number {0:?}")]
+    InvalidNumber(String),
+    #[error("score is newer than the clock")]
+    FutureScore,
+}
+
+/// A parse failure, located by 1-based line and column (field) numbers. For
+/// `table.txt`, columns are as described in `data/README.table.md`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}, column {column}: {kind}")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    /// Locates a [`ParseErrorKind`] at `line` and `column`, for `map_err`.
+    pub(crate) fn at(line: usize, column: usize) -> impl Fn(ParseErrorKind) -> Self {
+        move |kind| Self { line, column, kind }
+    }
+}
+
+/// Asserts that parsing each text fails with the error paired with it.
+#[cfg(test)]
+pub(crate) fn assert_parse_errors<T>(cases: &[(&str, ParseError)])
+where
+    T: std::str::FromStr<Err = ParseError> + std::fmt::Debug,
+{
+    for (text, expected) in cases {
+        assert_eq!(text.parse::<T>().unwrap_err(), *expected, "{text:?}");
+    }
+}
+
+/// Why a user dictionary or selection history file could not be loaded.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Why a `.dat` file could not be loaded.
+#[derive(Debug, Error)]
+pub enum DataError {
+    #[error("not a congkit data file")]
+    BadMagic,
+    #[error("data file has schema version {found}, but this build reads version {supported}; rebuild it from table.txt")]
+    UnsupportedSchema { found: u32, supported: u32 },
+    #[error("data file is truncated")]
+    Truncated,
+    #[error("data file has no {requested:?} codes (it has {available:?})")]
+    MissingVersion {
+        requested: CongkitVersion,
+        available: Vec<CongkitVersion>,
+    },
+    #[error("data file is corrupt: {0}")]
+    Corrupt(#[from] bitcode::Error),
+    #[error("data file is corrupt: entry {index} has an invalid code {value:#x}")]
+    InvalidCode { index: usize, value: u32 },
+    #[error("data file is corrupt: {array} record {index} points outside the file")]
+    BadRecord { array: &'static str, index: usize },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Why a string is not a [`CangjieCode`](crate::CangjieCode).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CodeError {
+    #[error("code is empty")]
+    Empty,
+    #[error("code has {0} keys, more than {max}", max = crate::CangjieCode::MAX_LEN)]
+    TooLong(usize),
+    #[error("{0:?} is not a Cangjie key")]
+    InvalidKey(char),
+}
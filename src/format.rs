@@ -0,0 +1,358 @@
+//! The `.dat` container: compiled table entries behind a header describing
+//! how they were built.
+//!
+//! Layout:
+//!
+//! | bytes | contents                                        |
+//! |-------|-------------------------------------------------|
+//! | 8     | [`MAGIC`]                                       |
+//! | 4     | schema version, little-endian `u32`             |
+//! | 4     | header length `n`, little-endian `u32`          |
+//! | `n`   | bitcode-encoded [`DataHeader`]                  |
+//! | rest  | bitcode-encoded entries                         |
+//!
+//! Memory-mapped tables (see [`MappedDB`](crate::MappedDB)) use the same
+//! prefix with their own magic, followed by fixed-size records instead of
+//! bitcode.
+//!
+//! The schema version is bumped whenever [`Entry`], [`DataHeader`] or the
+//! mapped record layout change shape, so old files are rejected instead of
+//! decoding into garbage.
+
+use crate::{CangjieCode, CongkitDB, CongkitFilter, CongkitVersion, DataError, Entry};
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// The first bytes of every `.dat` file.
+pub const MAGIC: [u8; 8] = *b"CONGKIT\0";
+
+/// The schema version this build reads and writes.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// FNV-1a, enough to tell whether a `.dat` file was built from a given
+/// `table.txt` without pulling in a hashing crate.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// What a `.dat` file was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Encode, Decode)]
+pub struct DataHeader {
+    /// Checksum of the `table.txt` the entries were parsed from.
+    pub source_checksum: u64,
+    /// The filter the entries were selected with.
+    pub filter: CongkitFilter,
+    /// The Cangjie versions with codes in the file: `V3`, `V5` or both.
+    pub versions: Vec<CongkitVersion>,
+    /// Number of entries.
+    pub entries: u64,
+}
+
+impl DataHeader {
+    /// Whether the file was built from `source`.
+    pub fn matches_source(&self, source: &str) -> bool {
+        self.source_checksum == checksum(source.as_bytes())
+    }
+
+    /// Whether the file has codes for lookups in `version`.
+    pub fn has_version(&self, version: CongkitVersion) -> bool {
+        version == CongkitVersion::Either && !self.versions.is_empty()
+            || self.versions.contains(&version)
+    }
+
+    /// Reads the header of a `.dat` file without decoding its entries.
+    pub fn read(data: &[u8]) -> Result<Self, DataError> {
+        Ok(split(data, &MAGIC)?.0)
+    }
+}
+
+/// An [`Entry`] as stored in a `.dat` file, with codes as their packed
+/// `u32`s so they can be checked when loading.
+#[derive(Encode, Decode)]
+struct EntryRecord {
+    traditional: char,
+    simplified: Option<char>,
+    chinese: bool,
+    big5: bool,
+    hkscs: bool,
+    taiwanese: bool,
+    kanji: bool,
+    hiragana: bool,
+    katakana: bool,
+    punctuation: bool,
+    misc: bool,
+    v3: Vec<u32>,
+    v5: Vec<u32>,
+    shortcut: Option<String>,
+    order: i32,
+}
+
+impl From<&Entry> for EntryRecord {
+    fn from(entry: &Entry) -> Self {
+        let pack = |codes: &[CangjieCode]| codes.iter().map(|code| code.to_u32()).collect();
+        Self {
+            traditional: entry.traditional,
+            simplified: entry.simplified,
+            chinese: entry.chinese,
+            big5: entry.big5,
+            hkscs: entry.hkscs,
+            taiwanese: entry.taiwanese,
+            kanji: entry.kanji,
+            hiragana: entry.hiragana,
+            katakana: entry.katakana,
+            punctuation: entry.punctuation,
+            misc: entry.misc,
+            v3: pack(&entry.v3),
+            v5: pack(&entry.v5),
+            shortcut: entry.shortcut.clone(),
+            order: entry.order,
+        }
+    }
+}
+
+impl EntryRecord {
+    /// The entry, failing if record `index` holds a value that is not a
+    /// valid [`CangjieCode`].
+    fn into_entry(self, index: usize) -> Result<Entry, DataError> {
+        let unpack = |codes: Vec<u32>| {
+            codes
+                .into_iter()
+                .map(|value| {
+                    CangjieCode::from_u32(value).ok_or(DataError::InvalidCode { index, value })
+                })
+                .collect::<Result<Vec<CangjieCode>, DataError>>()
+        };
+        Ok(Entry {
+            traditional: self.traditional,
+            simplified: self.simplified,
+            chinese: self.chinese,
+            big5: self.big5,
+            hkscs: self.hkscs,
+            taiwanese: self.taiwanese,
+            kanji: self.kanji,
+            hiragana: self.hiragana,
+            katakana: self.katakana,
+            punctuation: self.punctuation,
+            misc: self.misc,
+            v3: unpack(self.v3)?,
+            v5: unpack(self.v5)?,
+            shortcut: self.shortcut,
+            order: self.order,
+        })
+    }
+}
+
+/// Splits a container starting with `magic` into its decoded header and
+/// the body that follows.
+pub(crate) fn split<'a>(
+    data: &'a [u8],
+    magic: &[u8; 8],
+) -> Result<(DataHeader, &'a [u8]), DataError> {
+    let rest = data.strip_prefix(magic).ok_or(DataError::BadMagic)?;
+    let (schema, rest) = take_u32(rest)?;
+    if schema != SCHEMA_VERSION {
+        return Err(DataError::UnsupportedSchema {
+            found: schema,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    let (len, rest) = take_u32(rest)?;
+    if rest.len() < len as usize {
+        return Err(DataError::Truncated);
+    }
+    let (header, entries) = rest.split_at(len as usize);
+    Ok((bitcode::decode(header)?, entries))
+}
+
+pub(crate) fn take_u32(data: &[u8]) -> Result<(u32, &[u8]), DataError> {
+    match data.split_first_chunk::<4>() {
+        Some((bytes, rest)) => Ok((u32::from_le_bytes(*bytes), rest)),
+        None => Err(DataError::Truncated),
+    }
+}
+
+/// Writes entries in the `.dat` format, recording the `table.txt` and
+/// filter they came from.
+///
+/// ```no_run
+/// # use congkit::{CongkitDB, CongkitFilter, DataWriter};
+/// let txt = std::fs::read_to_string("data/table.txt")?;
+/// let filter = CongkitFilter::all();
+/// let entries = CongkitDB::to_entries(&txt, &filter)?;
+/// std::fs::write("table.dat", DataWriter::new(&txt, filter).write(&entries))?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataWriter {
+    source_checksum: u64,
+    filter: CongkitFilter,
+}
+
+impl DataWriter {
+    pub fn new(source: &str, filter: CongkitFilter) -> Self {
+        Self {
+            source_checksum: checksum(source.as_bytes()),
+            filter,
+        }
+    }
+
+    pub fn write(&self, entries: &[Entry]) -> Vec<u8> {
+        let mut data = self.container(&MAGIC, entries);
+        let records = entries.iter().map(EntryRecord::from).collect::<Vec<_>>();
+        data.extend_from_slice(&bitcode::encode(&records));
+        data
+    }
+
+    /// The magic, schema version and header of a container holding
+    /// `entries`, ready for the body to be appended.
+    pub(crate) fn container(&self, magic: &[u8; 8], entries: &[Entry]) -> Vec<u8> {
+        let versions = [CongkitVersion::V3, CongkitVersion::V5]
+            .into_iter()
+            .filter(|version| {
+                entries
+                    .iter()
+                    .any(|entry| entry.codes(*version).next().is_some())
+            })
+            .collect();
+        let header = bitcode::encode(&DataHeader {
+            source_checksum: self.source_checksum,
+            filter: self.filter.clone(),
+            versions,
+            entries: entries.len() as u64,
+        });
+        let mut data = magic.to_vec();
+        data.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+        data.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        data.extend_from_slice(&header);
+        data
+    }
+}
+
+impl CongkitDB {
+    /// Loads a `.dat` file written by [`DataWriter`]. `filter` is applied on
+    /// top of the filter the file was built with.
+    pub fn from_data(
+        data: &[u8],
+        version: CongkitVersion,
+        filter: CongkitFilter,
+    ) -> Result<Self, DataError> {
+        let (header, entries) = split(data, &MAGIC)?;
+        if !header.has_version(version) {
+            return Err(DataError::MissingVersion {
+                requested: version,
+                available: header.versions,
+            });
+        }
+        let records: Vec<EntryRecord> = bitcode::decode(entries)?;
+        let mut entries = Vec::with_capacity(records.len());
+        for (index, record) in records.into_iter().enumerate() {
+            let entry = record.into_entry(index)?;
+            if Self::apply_filters(&entry, &filter) {
+                entries.push(entry);
+            }
+        }
+        Ok(Self::from_entry_vec(entries, version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TXT: &str = "我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA 22308\n\
+                       你 你 1 1 0 0 1 0 0 0 0 onf onf NA 22461\n";
+
+    fn data() -> Vec<u8> {
+        let filter = CongkitFilter::all();
+        let entries = CongkitDB::to_entries(TXT, &filter).unwrap();
+        DataWriter::new(TXT, filter).write(&entries)
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = DataHeader::read(&data()).unwrap();
+        assert!(header.matches_source(TXT));
+        assert!(!header.matches_source("other"));
+        assert_eq!(header.filter, CongkitFilter::all());
+        assert_eq!(header.versions, [CongkitVersion::V3, CongkitVersion::V5]);
+        assert!(header.has_version(CongkitVersion::Either));
+        assert_eq!(header.entries, 2);
+
+        let db = CongkitDB::from_data(&data(), CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.get_characters("hqi").unwrap(), ['我']);
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut data = data();
+        data[0] = b'X';
+        assert!(matches!(DataHeader::read(&data), Err(DataError::BadMagic)));
+        assert!(matches!(
+            DataHeader::read(b"CONG"),
+            Err(DataError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn schema_mismatch() {
+        let mut data = data();
+        data[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            DataHeader::read(&data),
+            Err(DataError::UnsupportedSchema { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = data();
+        assert!(matches!(
+            DataHeader::read(&data[..MAGIC.len() + 2]),
+            Err(DataError::Truncated)
+        ));
+        assert!(matches!(
+            DataHeader::read(&data[..MAGIC.len() + 9]),
+            Err(DataError::Truncated)
+        ));
+        assert!(matches!(
+            CongkitDB::from_data(
+                &data[..data.len() - 1],
+                CongkitVersion::V3,
+                CongkitFilter::all()
+            ),
+            Err(DataError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_code() {
+        let entries = CongkitDB::to_entries(TXT, &CongkitFilter::all()).unwrap();
+        let mut records = entries.iter().map(EntryRecord::from).collect::<Vec<_>>();
+        records[1].v5.push(27);
+        let mut data = DataWriter::new(TXT, CongkitFilter::all()).container(&MAGIC, &entries);
+        data.extend_from_slice(&bitcode::encode(&records));
+        assert!(matches!(
+            CongkitDB::from_data(&data, CongkitVersion::V3, CongkitFilter::all()),
+            Err(DataError::InvalidCode {
+                index: 1,
+                value: 27
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_version() {
+        let txt = "我 我 1 1 0 0 1 0 0 0 0 hqi NA NA 22308\n";
+        let entries = CongkitDB::to_entries(txt, &CongkitFilter::all()).unwrap();
+        let data = DataWriter::new(txt, CongkitFilter::all()).write(&entries);
+        assert!(CongkitDB::from_data(&data, CongkitVersion::V3, CongkitFilter::all()).is_ok());
+        assert!(matches!(
+            CongkitDB::from_data(&data, CongkitVersion::V5, CongkitFilter::all()),
+            Err(DataError::MissingVersion { .. })
+        ));
+    }
+}
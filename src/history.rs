@@ -0,0 +1,265 @@
+//! Learning candidate ranking from the user's selections.
+
+use crate::{CongkitDB, LoadError, ParseError, ParseErrorKind};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score {
+    value: f64,
+    /// The clock reading when `value` was last updated.
+    updated: u64,
+}
+
+/// How often each character was committed for each typed input, with older
+/// selections decaying so that recent habits win.
+///
+/// Time is counted in selections: a score halves after `half_life` further
+/// selections of anything.
+///
+/// The file format is plain text: a `clock <n> <half_life>` line, then one
+/// `<input> <character> <score> <updated>` line per score. Lines starting
+/// with `#` are comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionHistory {
+    scores: HashMap<String, HashMap<char, Score>>,
+    clock: u64,
+    half_life: u64,
+}
+
+impl Default for SelectionHistory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_HALF_LIFE)
+    }
+}
+
+impl SelectionHistory {
+    pub const DEFAULT_HALF_LIFE: u64 = 500;
+
+    /// Scores below this are forgotten when saving.
+    const MIN_SCORE: f64 = 0.01;
+
+    pub fn new(half_life: u64) -> Self {
+        Self {
+            scores: HashMap::new(),
+            clock: 0,
+            half_life: half_life.max(1),
+        }
+    }
+
+    fn decayed(&self, score: &Score) -> f64 {
+        let age = (self.clock - score.updated) as f64;
+        score.value * 0.5f64.powf(age / self.half_life as f64)
+    }
+
+    /// Records that `character` was committed after typing `input`.
+    ///
+    /// Returns `false`, recording nothing, if the pair could not be saved and
+    /// read back: `input` is empty, starts with `#` or contains whitespace, or
+    /// `character` is whitespace.
+    pub fn record(&mut self, input: &str, character: char) -> bool {
+        if input.is_empty()
+            || input.starts_with('#')
+            || input.contains(char::is_whitespace)
+            || character.is_whitespace()
+        {
+            return false;
+        }
+        let value = self.boost(input, character) + 1.0;
+        let updated = self.clock;
+        self.scores
+            .entry(input.to_string())
+            .or_default()
+            .insert(character, Score { value, updated });
+        self.clock += 1;
+        true
+    }
+
+    /// The current, decayed score of `character` for `input`; 0 if it was
+    /// never selected.
+    pub fn boost(&self, input: &str, character: char) -> f64 {
+        self.scores
+            .get(input)
+            .and_then(|chars| chars.get(&character))
+            .map_or(0.0, |score| self.decayed(score))
+    }
+
+    /// Moves previously selected characters ahead, highest score first,
+    /// keeping the original order among equal scores.
+    pub fn rerank(&self, input: &str, chars: &mut [char]) {
+        if !self.scores.contains_key(input) {
+            return;
+        }
+        chars.sort_by(|a, b| self.boost(input, *b).total_cmp(&self.boost(input, *a)));
+    }
+
+    pub fn forget(&mut self) {
+        self.scores.clear();
+        self.clock = 0;
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for SelectionHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "clock {} {}", self.clock, self.half_life)?;
+        let mut inputs = self.scores.keys().collect::<Vec<&String>>();
+        inputs.sort();
+        for input in inputs {
+            let mut chars = self.scores[input].iter().collect::<Vec<_>>();
+            chars.sort_by_key(|(c, _)| **c);
+            for (character, score) in chars {
+                if self.decayed(score) >= Self::MIN_SCORE {
+                    writeln!(
+                        f,
+                        "{} {} {} {}",
+                        input, character, score.value, score.updated
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SelectionHistory {
+    fn parse_line(
+        history: &mut Option<Self>,
+        fields: &[&str],
+        line: usize,
+    ) -> Result<(), ParseError> {
+        let err = |column: usize| ParseError::at(line, column);
+        let number = |column: usize| {
+            let field = fields[column - 1];
+            field
+                .parse()
+                .map_err(|_| err(column)(ParseErrorKind::InvalidNumber(field.to_string())))
+        };
+        let Some(history) = history else {
+            if fields.len() != 3 || fields[0] != "clock" {
+                return Err(err(1)(ParseErrorKind::MissingClock));
+            }
+            let mut parsed = Self::new(number(3)?);
+            parsed.clock = number(2)?;
+            *history = Some(parsed);
+            return Ok(());
+        };
+        if fields.len() != 4 {
+            return Err(err(fields.len().min(4) + 1)(ParseErrorKind::FieldCount {
+                expected: 4,
+                found: fields.len(),
+            }));
+        }
+        let character = CongkitDB::parse_char(fields[1]).map_err(err(2))?;
+        let score = Score {
+            value: fields[2]
+                .parse()
+                .map_err(|_| err(3)(ParseErrorKind::InvalidNumber(fields[2].to_string())))?,
+            updated: number(4)?,
+        };
+        if score.updated > history.clock {
+            return Err(err(4)(ParseErrorKind::FutureScore));
+        }
+        history
+            .scores
+            .entry(fields[0].to_string())
+            .or_default()
+            .insert(character, score);
+        Ok(())
+    }
+}
+
+impl FromStr for SelectionHistory {
+    type Err = ParseError;
+
+    /// Fails with [`ParseErrorKind::MissingClock`] on the line after the last
+    /// if there is no clock line at all.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut history = None;
+        let mut lines = 0;
+        for (i, line) in s.lines().enumerate() {
+            lines = i + 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split(' ').collect::<Vec<&str>>();
+            Self::parse_line(&mut history, &fields, i + 1)?;
+        }
+        history.ok_or_else(|| ParseError::at(lines + 1, 1)(ParseErrorKind::MissingClock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SelectionHistory;
+    use crate::error::assert_parse_errors;
+    use crate::{ParseError, ParseErrorKind};
+
+    #[test]
+    fn round_trips() {
+        let mut history = SelectionHistory::new(10);
+        assert!(history.record("a", '曰'));
+        assert!(history.record("a", '曰'));
+        assert!(history.record("ab", '明'));
+        let parsed: SelectionHistory = history.to_string().parse().unwrap();
+        assert_eq!(parsed, history);
+        let mut chars = ['日', '曰'];
+        parsed.rerank("a", &mut chars);
+        assert_eq!(chars, ['曰', '日']);
+    }
+
+    #[test]
+    fn refuses_selections_that_cannot_be_saved() {
+        let mut history = SelectionHistory::default();
+        assert!(!history.record("", '日'));
+        assert!(!history.record("#a", '日'));
+        assert!(!history.record("a b", '日'));
+        assert!(!history.record("a", ' '));
+        assert!(!history.record("a", '\u{3000}'));
+        assert_eq!(history, SelectionHistory::default());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let number = |field: &str| ParseErrorKind::InvalidNumber(field.to_string());
+        assert_parse_errors::<SelectionHistory>(&[
+            (
+                "# comment\n",
+                ParseError::at(2, 1)(ParseErrorKind::MissingClock),
+            ),
+            (
+                "a 日 1 0",
+                ParseError::at(1, 1)(ParseErrorKind::MissingClock),
+            ),
+            ("clock 5 x", ParseError::at(1, 3)(number("x"))),
+            (
+                "clock 5 10\na 日 high 0",
+                ParseError::at(2, 3)(number("high")),
+            ),
+            (
+                "clock 5 10\na 日 1 6",
+                ParseError::at(2, 4)(ParseErrorKind::FutureScore),
+            ),
+            (
+                "clock 5 10\na 日日 1 0",
+                ParseError::at(2, 2)(ParseErrorKind::NotACharacter("日日".to_string())),
+            ),
+            (
+                "clock 5 10\n\na 日 1",
+                ParseError::at(3, 4)(ParseErrorKind::FieldCount {
+                    expected: 4,
+                    found: 3,
+                }),
+            ),
+        ]);
+    }
+}
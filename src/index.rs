@@ -0,0 +1,94 @@
+//! Sorted code arrays answering exact, prefix, suffix and wildcard lookups
+//! with binary searches instead of scanning every entry.
+
+use crate::pattern::Pattern;
+use crate::{rank, ranked_characters, CangjieCode};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexEntry {
+    pub(crate) code: CangjieCode,
+    pub(crate) character: char,
+    pub(crate) order: i32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CodeIndex {
+    /// Every (code, character) pair, sorted by code.
+    by_code: Vec<IndexEntry>,
+    /// Reversed codes paired with their position in `by_code`, sorted by the
+    /// reversed code so suffixes become prefixes.
+    by_suffix: Vec<(CangjieCode, usize)>,
+}
+
+/// The range of `items` whose key starts with `prefix`, given `items` sorted
+/// by that key.
+fn prefix_range<T>(
+    items: &[T],
+    prefix: &CangjieCode,
+    key: impl Fn(&T) -> &CangjieCode,
+) -> Range<usize> {
+    let start = items.partition_point(|item| key(item) < prefix);
+    let len = items[start..].partition_point(|item| key(item).starts_with(prefix));
+    start..start + len
+}
+
+impl CodeIndex {
+    pub(crate) fn new(mut by_code: Vec<IndexEntry>) -> Self {
+        by_code
+            .sort_by(|a, b| (&a.code, a.order, a.character).cmp(&(&b.code, b.order, b.character)));
+        by_code.dedup();
+        let mut by_suffix = by_code
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.code.reversed(), i))
+            .collect::<Vec<(CangjieCode, usize)>>();
+        by_suffix.sort();
+        Self { by_code, by_suffix }
+    }
+
+    pub(crate) fn entries(&self) -> &[IndexEntry] {
+        &self.by_code
+    }
+
+    /// The range of `entries()` whose code starts with `prefix`.
+    pub(crate) fn prefix_range(&self, prefix: &CangjieCode) -> Range<usize> {
+        self.narrow(0..self.by_code.len(), prefix)
+    }
+
+    /// Narrows `range`, already sharing a prefix of `prefix`, down to the codes
+    /// that start with all of `prefix`.
+    pub(crate) fn narrow(&self, range: Range<usize>, prefix: &CangjieCode) -> Range<usize> {
+        let sub = prefix_range(&self.by_code[range.clone()], prefix, |entry| &entry.code);
+        range.start + sub.start..range.start + sub.end
+    }
+
+    /// Entries whose code matches `pattern`, narrowed to the codes sharing its
+    /// literal prefix or suffix (whichever is more selective) before matching.
+    pub(crate) fn matching<'a>(
+        &'a self,
+        pattern: &'a Pattern,
+    ) -> Box<dyn Iterator<Item = &'a IndexEntry> + 'a> {
+        let prefix = self.prefix_range(&pattern.literal_prefix());
+        let suffix_literal = pattern.literal_suffix().reversed();
+        let suffix = prefix_range(&self.by_suffix, &suffix_literal, |(code, _)| code);
+        let candidates: Box<dyn Iterator<Item = &IndexEntry>> = if suffix.len() < prefix.len() {
+            Box::new(
+                self.by_suffix[suffix]
+                    .iter()
+                    .map(|(_, i)| &self.by_code[*i]),
+            )
+        } else {
+            Box::new(self.by_code[prefix].iter())
+        };
+        Box::new(candidates.filter(|entry| pattern.matches_code(&entry.code)))
+    }
+
+    /// The characters of `hits`, ranked by [`ranked_characters`].
+    pub(crate) fn sorted_characters<'a>(hits: impl Iterator<Item = &'a IndexEntry>) -> Vec<char> {
+        ranked_characters(
+            hits.map(|entry| (rank(entry.order), entry.character))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,157 @@
+//! The 26 Cangjie keys and the radicals they name.
+
+use std::fmt;
+
+/// The group a key's radical belongs to in the Cangjie layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCategory {
+    /// 哲理類, `a`–`g`: the sun, moon and five elements.
+    Philosophical,
+    /// 筆畫類, `h`–`n`: basic strokes.
+    Stroke,
+    /// 人體類, `o`–`r`: parts of the body.
+    Body,
+    /// 字形類, `s`–`y` except `x`: character shapes.
+    Shape,
+    /// `x` (難), for characters that are hard to decompose, and `z` (重),
+    /// for telling apart characters that share a code.
+    Special,
+}
+
+/// A key on a Cangjie keyboard, `a` to `z`.
+///
+/// Displays as its letter (`q`), or as its radical with `{:#}` (`手`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CangjieKey {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+}
+
+use CangjieKey::*;
+
+impl CangjieKey {
+    /// Every key, in alphabetical order.
+    pub const ALL: [Self; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+
+    const RADICALS: [char; 26] = [
+        '日', '月', '金', '木', '水', '火', '土', '竹', '戈', '十', '大', '中', '一', '弓', '人',
+        '心', '手', '口', '尸', '廿', '山', '女', '田', '難', '卜', '重',
+    ];
+
+    /// 輔助字形: the other shapes each key stands for inside a character.
+    const SHAPES: [&'static [char]; 26] = [
+        &['曰'],
+        &['冂', '⺼', '⺆', '爫'],
+        &['丷', '八'],
+        &['寸'],
+        &['氵', '又'],
+        &['灬', '小'],
+        &['士'],
+        &['丿', '⺮'],
+        &['丶', '厶'],
+        &['宀'],
+        &['乂', 'ナ'],
+        &['丨', '衤'],
+        &['厂', '工'],
+        &['乛'],
+        &['亻'],
+        &['忄', '⺗'],
+        &['扌', '龵'],
+        &[],
+        &['匚'],
+        &['艹', '卄'],
+        &['凵'],
+        &['𠃋'],
+        &['囗'],
+        &[],
+        &['⺊', '亠'],
+        &[],
+    ];
+
+    /// The key typed with `letter`, `a`–`z`.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        letter
+            .is_ascii_lowercase()
+            .then(|| Self::ALL[usize::from(letter as u8 - b'a')])
+    }
+
+    /// The key whose radical is `radical`.
+    pub fn from_radical(radical: char) -> Option<Self> {
+        Self::RADICALS
+            .iter()
+            .position(|r| *r == radical)
+            .map(|i| Self::ALL[i])
+    }
+
+    /// The lowercase letter the key is typed with.
+    pub fn letter(self) -> char {
+        (b'a' + self as u8) as char
+    }
+
+    /// The radical the key is named after, e.g. `手` for `q`.
+    pub fn radical(self) -> char {
+        Self::RADICALS[self as usize]
+    }
+
+    /// The auxiliary shapes (輔助字形) the key also stands for, e.g. `扌`
+    /// and `龵` for `q`. Empty for keys that only stand for their radical.
+    pub fn shapes(self) -> &'static [char] {
+        Self::SHAPES[self as usize]
+    }
+
+    pub fn category(self) -> KeyCategory {
+        match self {
+            A | B | C | D | E | F | G => KeyCategory::Philosophical,
+            H | I | J | K | L | M | N => KeyCategory::Stroke,
+            O | P | Q | R => KeyCategory::Body,
+            S | T | U | V | W | Y => KeyCategory::Shape,
+            X | Z => KeyCategory::Special,
+        }
+    }
+}
+
+impl fmt::Display for CangjieKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.alternate() {
+            true => write!(f, "{}", self.radical()),
+            false => write!(f, "{}", self.letter()),
+        }
+    }
+}
+
+impl TryFrom<char> for CangjieKey {
+    type Error = crate::CodeError;
+
+    /// Accepts either a letter or a radical.
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Self::from_letter(c)
+            .or_else(|| Self::from_radical(c))
+            .ok_or(crate::CodeError::InvalidKey(c))
+    }
+}
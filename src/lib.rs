@@ -1,17 +1,57 @@
-use anyhow::Result;
-use bimap::BiMap;
 use bitcode::{Decode, Encode};
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Mutex, OnceLock};
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[cfg(any(feature = "embed-full", feature = "embed-trimmed"))]
+mod builtin;
+mod code;
+mod complete;
+mod decode;
+mod diff;
+mod encode;
+mod error;
+mod format;
+mod history;
+mod index;
+mod key;
+mod mapped;
+mod overlay;
+pub mod pattern;
+mod quick;
+mod session;
+mod simplified;
+
+#[cfg(any(feature = "embed-full", feature = "embed-trimmed"))]
+pub use builtin::BuiltinTable;
+pub use code::CangjieCode;
+pub use complete::{Completion, PrefixCursor};
+pub use decode::{Decoded, DecodedPosition};
+pub use diff::{CodeChange, Collision, VersionDiff};
+pub use encode::{EncodeFormat, Segment};
+pub use error::{CodeError, DataError, LoadError, ParseError, ParseErrorKind};
+pub use format::{DataHeader, DataWriter, MAGIC, SCHEMA_VERSION};
+pub use history::SelectionHistory;
+pub use key::{CangjieKey, KeyCategory};
+pub use mapped::{MappedDB, MAPPED_MAGIC};
+pub use overlay::{Override, UserDictionary};
+pub use pattern::{Pattern, PatternError, PatternOptions};
+pub use session::{InputSession, Key, SessionOutput};
+
+use index::{CodeIndex, IndexEntry};
+use quick::QuickIndex;
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CongkitVersion {
     V3,
     V5,
+    /// Matches a code from either V3 or V5, for users who mix the two.
+    Either,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Encode, Decode)]
 pub struct CongkitFilter {
     pub chinese: bool,
     pub big5: bool,
@@ -74,10 +114,10 @@ impl CongkitFilter {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Encode, Decode, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Entry {
     traditional: char,
-    simplified: char,
+    simplified: Option<char>,
     chinese: bool,
     big5: bool,
     hkscs: bool,
@@ -87,13 +127,89 @@ pub struct Entry {
     katakana: bool,
     punctuation: bool,
     misc: bool,
-    v3: String,
-    v5: String,
-    code: String,
-    shortcut: String,
+    v3: Vec<CangjieCode>,
+    v5: Vec<CangjieCode>,
+    shortcut: Option<String>,
     order: i32,
 }
 
+/// Read-only access to a table line.
+impl Entry {
+    pub fn traditional(&self) -> char {
+        self.traditional
+    }
+
+    pub fn simplified(&self) -> Option<char> {
+        self.simplified
+    }
+
+    pub fn is_chinese(&self) -> bool {
+        self.chinese
+    }
+
+    pub fn is_big5(&self) -> bool {
+        self.big5
+    }
+
+    pub fn is_hkscs(&self) -> bool {
+        self.hkscs
+    }
+
+    pub fn is_taiwanese(&self) -> bool {
+        self.taiwanese
+    }
+
+    pub fn is_kanji(&self) -> bool {
+        self.kanji
+    }
+
+    pub fn is_hiragana(&self) -> bool {
+        self.hiragana
+    }
+
+    pub fn is_katakana(&self) -> bool {
+        self.katakana
+    }
+
+    pub fn is_punctuation(&self) -> bool {
+        self.punctuation
+    }
+
+    pub fn is_misc(&self) -> bool {
+        self.misc
+    }
+
+    pub fn v3(&self) -> &[CangjieCode] {
+        &self.v3
+    }
+
+    pub fn v5(&self) -> &[CangjieCode] {
+        &self.v5
+    }
+
+    /// The codes for `version`. For [`CongkitVersion::Either`], the V3 codes
+    /// are followed by any V5 codes not already among them.
+    pub fn codes(&self, version: CongkitVersion) -> impl Iterator<Item = CangjieCode> + '_ {
+        let (first, second): (&[CangjieCode], &[CangjieCode]) = match version {
+            CongkitVersion::V3 => (&self.v3, &[]),
+            CongkitVersion::V5 => (&self.v5, &[]),
+            CongkitVersion::Either => (&self.v3, &self.v5),
+        };
+        first
+            .iter()
+            .chain(second.iter().filter(move |code| !first.contains(code)))
+            .copied()
+    }
+
+    pub fn shortcut(&self) -> Option<&str> {
+        self.shortcut.as_deref()
+    }
+
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+}
+
 // #[derive(Debug, Deserialize, Serialize, Encode, Decode, PartialEq)]
 // pub struct EntryTrimmed {
 //     traditional: char,
@@ -106,139 +222,397 @@ pub struct Entry {
 //     order: i32,
 // }
 
+/// Sort key for an `order` value. Higher orders come first: Big5 characters
+/// count down from 一 at 23230, so common characters lead, and the table
+/// gives 0 to every character it has no ordering for.
+pub(crate) fn rank(order: i32) -> Reverse<i32> {
+    Reverse(order)
+}
+
+/// `items` in their original order, keeping only the first occurrence of
+/// each.
+pub(crate) fn unique<T: Copy + Eq + Hash>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(*item))
+        .collect()
+}
+
+/// Sorts `hits` by their sort key (typically led by [`rank`]), then by
+/// character so ties are stable across runs, and returns the characters,
+/// keeping only the first occurrence of a character hit several times.
+pub(crate) fn ranked_characters<K: Ord>(mut hits: Vec<(K, char)>) -> Vec<char> {
+    hits.sort();
+    unique(hits.into_iter().map(|(_, c)| c))
+}
+
+/// The lookup structures for one [`CongkitVersion`].
+#[derive(Debug)]
+struct VersionIndex {
+    codes: CodeIndex,
+    quick: QuickIndex,
+}
+
+impl VersionIndex {
+    fn new<'a>(entries: impl Iterator<Item = &'a Entry> + Clone, version: CongkitVersion) -> Self {
+        let codes = CodeIndex::new(
+            entries
+                .clone()
+                .flat_map(|entry| {
+                    entry.codes(version).map(|code| IndexEntry {
+                        code,
+                        character: entry.traditional,
+                        order: entry.order,
+                    })
+                })
+                .collect(),
+        );
+        let quick = QuickIndex::new(entries, version);
+        Self { codes, quick }
+    }
+}
+
 #[derive(Debug)]
 pub struct CongkitDB {
-    entries: HashMap<char, Entry>,
+    entries: HashMap<char, Vec<Entry>>,
+    /// Built on first use for each version, indexed by [`Self::slot`].
+    indexes: [OnceLock<VersionIndex>; 3],
+    shortcuts: HashMap<String, Vec<char>>,
+    traditional: HashMap<char, Vec<char>>,
+    /// Learned selections, shared with [`InputSession`]s that record into it.
+    history: Option<Mutex<SelectionHistory>>,
+    user_dictionary: Option<UserDictionary>,
+    /// The table's own entries for every character the user dictionary
+    /// touches (empty for added characters), restored when it changes.
+    base: HashMap<char, Vec<Entry>>,
     version: CongkitVersion,
-    radicals: BiMap<char, char>,
 }
 
 impl Default for CongkitDB {
     fn default() -> Self {
         Self {
             entries: HashMap::new(),
+            indexes: Default::default(),
+            shortcuts: HashMap::new(),
+            traditional: HashMap::new(),
+            history: None,
+            user_dictionary: None,
+            base: HashMap::new(),
             version: CongkitVersion::V3,
-            radicals: BiMap::from_iter([
-                ('日', 'a'),
-                ('月', 'b'),
-                ('金', 'c'),
-                ('木', 'd'),
-                ('水', 'e'),
-                ('火', 'f'),
-                ('土', 'g'),
-                ('竹', 'h'),
-                ('戈', 'i'),
-                ('十', 'j'),
-                ('大', 'k'),
-                ('中', 'l'),
-                ('一', 'm'),
-                ('弓', 'n'),
-                ('人', 'o'),
-                ('心', 'p'),
-                ('手', 'q'),
-                ('口', 'r'),
-                ('尸', 's'),
-                ('廿', 't'),
-                ('山', 'u'),
-                ('女', 'v'),
-                ('田', 'w'),
-                ('難', 'x'),
-                ('卜', 'y'),
-            ]),
         }
     }
 }
 
 impl CongkitDB {
-    pub fn get_radical(&self, key: &char) -> Option<char> {
-        self.radicals.get_by_right(key).copied()
+    /// The version used by lookups that do not take one.
+    pub fn version(&self) -> CongkitVersion {
+        self.version
+    }
+
+    /// Switches the version used by lookups that do not take one, without
+    /// reloading the table.
+    pub fn set_version(&mut self, version: CongkitVersion) {
+        self.version = version;
+        self.index_for(version);
+    }
+
+    fn slot(version: CongkitVersion) -> usize {
+        match version {
+            CongkitVersion::V3 => 0,
+            CongkitVersion::V5 => 1,
+            CongkitVersion::Either => 2,
+        }
+    }
+
+    fn index_for(&self, version: CongkitVersion) -> &VersionIndex {
+        self.indexes[Self::slot(version)]
+            .get_or_init(|| VersionIndex::new(self.entries.values().flatten(), version))
+    }
+
+    pub(crate) fn index(&self, version: CongkitVersion) -> &CodeIndex {
+        &self.index_for(version).codes
+    }
+
+    /// The number of entries (table lines) loaded. A character listed on
+    /// several lines is counted once per line.
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
     }
 
-    pub fn get_key(&self, radical: &char) -> Option<char> {
-        self.radicals.get_by_left(radical).copied()
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 
+    /// Returns the first entry for `character`.
+    pub fn get_entry(&self, character: &char) -> Option<&Entry> {
+        self.entries.get(character)?.first()
+    }
+
+    /// Returns every entry for `character`, one per table line.
+    pub fn get_entries(&self, character: &char) -> &[Entry] {
+        self.entries.get(character).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates over every entry, in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values().flatten()
+    }
+
+    /// Iterates over every (code, character) pair of the active version,
+    /// sorted by code.
+    pub fn codes(&self) -> impl Iterator<Item = (CangjieCode, char)> + '_ {
+        self.index(self.version)
+            .entries()
+            .iter()
+            .map(|entry| (entry.code, entry.character))
+    }
+
+    pub fn get_radical(&self, key: CangjieKey) -> char {
+        key.radical()
+    }
+
+    pub fn get_key(&self, radical: &char) -> Option<CangjieKey> {
+        CangjieKey::from_radical(*radical)
+    }
+
+    pub fn get_shapes(&self, key: CangjieKey) -> &'static [char] {
+        key.shapes()
+    }
+
+    /// Replaces every key letter in `code` with its radical, leaving
+    /// anything else (wildcards, spaces) as it is.
     pub fn get_radicals(&self, code: &str) -> String {
         code.chars()
-            .map(|c| match self.get_radical(&c) {
-                Some(ch) => ch,
-                None => c,
+            .map(|c| CangjieKey::from_letter(c).map_or(c, CangjieKey::radical))
+            .collect::<String>()
+    }
+
+    /// Like [`get_radicals`](Self::get_radicals), with each radical followed
+    /// by the auxiliary shapes of its key, e.g. `竹(丿⺮)手(扌龵)戈(丶厶)`
+    /// for `hqi`.
+    pub fn get_radicals_with_shapes(&self, code: &str) -> String {
+        code.chars()
+            .map(|c| match CangjieKey::from_letter(c) {
+                Some(key) if !key.shapes().is_empty() => {
+                    format!(
+                        "{}({})",
+                        key.radical(),
+                        key.shapes().iter().collect::<String>()
+                    )
+                }
+                Some(key) => key.radical().to_string(),
+                None => c.to_string(),
             })
             .collect::<String>()
     }
 
-    pub fn get_code(&self, character: &char) -> Option<String> {
-        Some(self.entries.get(character)?.code.clone())
+    /// Returns every code of `character` in the active version, across all
+    /// of its table lines, or `None` if the character is not in the database.
+    /// A character that is present but has no code in the version yields an
+    /// empty list.
+    pub fn get_code(&self, character: &char) -> Option<Vec<CangjieCode>> {
+        self.get_code_in(character, self.version)
+    }
+
+    pub fn get_code_in(
+        &self,
+        character: &char,
+        version: CongkitVersion,
+    ) -> Option<Vec<CangjieCode>> {
+        Some(unique(
+            self.entries
+                .get(character)?
+                .iter()
+                .flat_map(|e| e.codes(version)),
+        ))
     }
 
-    pub fn get_codes(&self, chars: Vec<char>) -> Vec<Option<String>> {
+    pub fn get_codes(&self, chars: Vec<char>) -> Vec<Option<Vec<CangjieCode>>> {
         chars
             .iter()
             .map(|c| self.get_code(c))
-            .collect::<Vec<Option<String>>>()
-    }
-
-    pub fn get_characters(&self, code: &str) -> Result<Vec<char>> {
-        let re = Regex::new(&format!("^{}$", code.replace('*', ".+")))?;
-        let mut filt = self
-            .entries
-            .values()
-            .filter(|entry| re.is_match(&entry.code))
-            .collect::<Vec<&Entry>>();
-        filt.sort_by(|a, b| a.order.cmp(&b.order));
-        Ok(filt
-            .iter()
-            .map(|entry| entry.traditional)
-            .collect::<Vec<char>>())
-    }
-
-    pub fn get_chars_mult(&self, codes: Vec<String>) -> Result<HashMap<String, Vec<char>>> {
-        let mut chars: HashMap<String, Vec<&Entry>> = HashMap::new();
-        let mut regexes: HashMap<String, Regex> = HashMap::new();
-        for c in codes.into_iter() {
-            chars.insert(c.clone(), Vec::new());
-            regexes.insert(
-                c.clone(),
-                Regex::new(&format!("^{}$", c.replace('*', ".+")))?,
-            );
+            .collect::<Vec<Option<Vec<CangjieCode>>>>()
+    }
+
+    /// Returns the characters with a code in the active version matching
+    /// `pattern`, ranked by `order`. See [`pattern`] for the wildcard syntax.
+    pub fn get_characters(&self, pattern: &str) -> Result<Vec<char>, PatternError> {
+        self.get_characters_in(pattern, self.version)
+    }
+
+    pub fn get_characters_in(
+        &self,
+        pattern: &str,
+        version: CongkitVersion,
+    ) -> Result<Vec<char>, PatternError> {
+        let mut chars = self.get_characters_matching(&Pattern::parse(pattern)?, version);
+        self.rerank(pattern, &mut chars);
+        Ok(chars)
+    }
+
+    pub fn get_characters_matching(&self, pattern: &Pattern, version: CongkitVersion) -> Vec<char> {
+        CodeIndex::sorted_characters(self.index(version).matching(pattern))
+    }
+
+    pub fn get_chars_mult(
+        &self,
+        codes: Vec<String>,
+    ) -> Result<HashMap<String, Vec<char>>, PatternError> {
+        codes
+            .into_iter()
+            .map(|code| {
+                let chars = self.get_characters(&code)?;
+                Ok((code, chars))
+            })
+            .collect()
+    }
+
+    /// Returns up to `limit` candidates for the key buffer `keys`: exact
+    /// matches first, then codes that continue it. An empty buffer has no
+    /// candidates.
+    pub fn complete(&self, keys: &str, limit: usize) -> Result<Completion, PatternError> {
+        if keys.is_empty() {
+            return Ok(Completion::default());
         }
-        for ent in self.entries.values() {
-            for (code, re) in regexes.iter() {
-                if re.is_match(&ent.code) {
-                    chars.get_mut(code).unwrap().push(ent);
-                }
+        let mut completion = self.cursor(keys)?.candidates(usize::MAX);
+        self.rerank(keys, &mut completion.exact);
+        self.rerank(keys, &mut completion.completions);
+        completion.truncate(limit);
+        Ok(completion)
+    }
+
+    /// Starts a completion cursor at `keys` in the active version, which can
+    /// then be extended or shortened one key at a time.
+    pub fn cursor(&self, keys: &str) -> Result<PrefixCursor<'_>, PatternError> {
+        self.cursor_in(keys, self.version)
+    }
+
+    pub fn cursor_in(
+        &self,
+        keys: &str,
+        version: CongkitVersion,
+    ) -> Result<PrefixCursor<'_>, PatternError> {
+        PrefixCursor::new(self.index(version), keys)
+    }
+
+    /// Returns the Quick (速成) codes of `character`: the first and last keys
+    /// of each of its full codes.
+    pub fn get_quick_code(&self, character: &char) -> Option<Vec<CangjieCode>> {
+        Some(unique(
+            self.get_code(character)?.into_iter().map(quick::quick_code),
+        ))
+    }
+
+    /// Returns the candidates for a one- or two-key Quick (速成) input, most
+    /// likely first.
+    pub fn get_quick_characters(&self, keys: &str) -> Result<Vec<char>, PatternError> {
+        if let Some((position, character)) = keys
+            .chars()
+            .enumerate()
+            .find(|(_, c)| !c.is_ascii_lowercase())
+        {
+            return Err(PatternError::InvalidCharacter {
+                position,
+                character,
+            });
+        }
+        match keys.len() {
+            0 => Err(PatternError::Empty),
+            1 | 2 => {
+                let quick = CangjieCode::new(keys).expect("validated above");
+                let mut chars = self.index_for(self.version).quick.get(quick).to_vec();
+                self.rerank(keys, &mut chars);
+                Ok(chars)
             }
+            min_len => Err(PatternError::TooLong {
+                min_len,
+                max_len: 2,
+            }),
+        }
+    }
+
+    /// Turns on learning from selections, starting from `history` (e.g. one
+    /// loaded with [`SelectionHistory::load`]). Lookups then move characters
+    /// the user picked for the same input ahead of the table's ranking.
+    pub fn set_history(&mut self, history: SelectionHistory) {
+        self.history = Some(Mutex::new(history));
+    }
+
+    /// Turns off learning, returning what was learned.
+    pub fn take_history(&mut self) -> Option<SelectionHistory> {
+        self.history
+            .take()
+            .map(|history| history.into_inner().unwrap())
+    }
+
+    /// A copy of the learned selections, e.g. for saving.
+    pub fn history(&self) -> Option<SelectionHistory> {
+        Some(self.history.as_ref()?.lock().unwrap().clone())
+    }
+
+    /// Records that `character` was committed after typing `input`. Does
+    /// nothing unless learning is turned on. Returns `false` if nothing was
+    /// recorded; see [`SelectionHistory::record`].
+    pub fn record_selection(&self, input: &str, character: char) -> bool {
+        match &self.history {
+            Some(history) => history.lock().unwrap().record(input, character),
+            None => false,
         }
-        for matches in chars.values_mut() {
-            matches.sort_by(|a, b| a.order.cmp(&b.order));
+    }
+
+    pub(crate) fn rerank(&self, input: &str, chars: &mut [char]) {
+        if let Some(history) = &self.history {
+            history.lock().unwrap().rerank(input, chars);
         }
-        Ok(chars
-            .into_iter()
-            .map(|(k, v)| {
-                (
-                    k,
-                    v.iter().map(|ent| ent.traditional).collect::<Vec<char>>(),
-                )
-            })
-            .collect::<HashMap<String, Vec<char>>>())
+    }
+
+    /// Returns the characters reachable through the quick-input shortcut
+    /// `key` (e.g. `,`, `'` or `" "` for the space bar), ranked by `order`.
+    pub fn get_shortcut_characters(&self, key: &str) -> Vec<char> {
+        self.shortcuts.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Returns the quick-input shortcut that produces `character`, if any.
+    pub fn get_shortcut(&self, character: &char) -> Option<String> {
+        self.entries
+            .get(character)?
+            .iter()
+            .find_map(|entry| entry.shortcut.clone())
     }
 
     fn from_entry_vec(entry_vec: Vec<Entry>, version: CongkitVersion) -> Self {
-        let entries = entry_vec
-            .into_iter()
-            .map(|mut entry| {
-                entry.code = match version {
-                    CongkitVersion::V3 => entry.v3.clone(),
-                    CongkitVersion::V5 => entry.v5.clone(),
-                };
-                (entry.traditional, entry)
-            })
-            .collect::<HashMap<char, Entry>>();
-        Self {
+        let mut entries: HashMap<char, Vec<Entry>> = HashMap::new();
+        for entry in entry_vec.into_iter() {
+            entries.entry(entry.traditional).or_default().push(entry);
+        }
+        let mut db = Self {
             entries,
             version,
             ..Default::default()
+        };
+        db.rebuild();
+        db
+    }
+
+    /// Recomputes everything derived from `entries` after they change.
+    fn rebuild(&mut self) {
+        let mut shortcut_hits: HashMap<String, Vec<(Reverse<i32>, char)>> = HashMap::new();
+        for entry in self.entries.values().flatten() {
+            if let Some(shortcut) = &entry.shortcut {
+                shortcut_hits
+                    .entry(shortcut.clone())
+                    .or_default()
+                    .push((rank(entry.order), entry.traditional));
+            }
         }
+        self.shortcuts = shortcut_hits
+            .into_iter()
+            .map(|(k, v)| (k, ranked_characters(v)))
+            .collect::<HashMap<String, Vec<char>>>();
+        self.traditional = simplified::traditional_forms(self.entries.values().flatten());
+        self.indexes = Default::default();
+        self.index_for(self.version);
     }
 
     fn apply_filters(entry: &Entry, filter: &CongkitFilter) -> bool {
@@ -253,52 +627,323 @@ impl CongkitDB {
             || (entry.misc && filter.misc)
     }
 
-    pub fn from_data(data: &[u8], version: CongkitVersion, filter: CongkitFilter) -> Result<Self> {
-        let entries_vec: Vec<Entry> = bitcode::decode(data)?;
-        let entries = entries_vec
-            .into_iter()
-            .filter(|entry| Self::apply_filters(entry, &filter))
-            .collect::<Vec<Entry>>();
-        Ok(Self::from_entry_vec(entries, version))
+    /// Number of space-separated fields on every `table.txt` line.
+    const FIELD_COUNT: usize = 15;
+
+    /// Maximum number of keys in a single Cangjie code.
+    pub const MAX_CODE_LEN: usize = CangjieCode::MAX_LEN;
+
+    fn parse_char(field: &str) -> Result<char, ParseErrorKind> {
+        let mut chars = field.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ParseErrorKind::NotACharacter(field.to_string())),
+        }
+    }
+
+    fn parse_bool(field: &str) -> Result<bool, ParseErrorKind> {
+        match field {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => Err(ParseErrorKind::NotABool(field.to_string())),
+        }
+    }
+
+    /// Splits a comma-separated code field, mapping the table's `NA`
+    /// placeholder to an empty list.
+    fn split_codes(field: &str) -> Result<Vec<CangjieCode>, ParseErrorKind> {
+        if field == "NA" {
+            return Ok(Vec::new());
+        }
+        field
+            .split(',')
+            .map(|code| {
+                code.parse()
+                    .map_err(|_| ParseErrorKind::InvalidCode(code.to_string()))
+            })
+            .collect()
     }
 
-    pub fn to_entries(txt: &str, filter: &CongkitFilter) -> Vec<Entry> {
+    fn parse_line(line: &str, line_no: usize) -> Result<Entry, ParseError> {
+        let fields = line.split(' ').collect::<Vec<&str>>();
+        let err = |column: usize| ParseError::at(line_no, column);
+        if let Some(column) = fields.iter().position(|field| field.is_empty()) {
+            return Err(err(column + 1)(ParseErrorKind::EmptyField));
+        }
+        if fields.len() != Self::FIELD_COUNT {
+            return Err(err(fields.len().min(Self::FIELD_COUNT) + 1)(
+                ParseErrorKind::FieldCount {
+                    expected: Self::FIELD_COUNT,
+                    found: fields.len(),
+                },
+            ));
+        }
+        let flag = |column: usize| Self::parse_bool(fields[column - 1]).map_err(err(column));
+        Ok(Entry {
+            traditional: Self::parse_char(fields[0]).map_err(err(1))?,
+            simplified: match fields[1] {
+                "NA" => None,
+                field => Some(Self::parse_char(field).map_err(err(2))?),
+            },
+            chinese: flag(3)?,
+            big5: flag(4)?,
+            hkscs: flag(5)?,
+            taiwanese: flag(6)?,
+            kanji: flag(7)?,
+            hiragana: flag(8)?,
+            katakana: flag(9)?,
+            punctuation: flag(10)?,
+            misc: flag(11)?,
+            v3: Self::split_codes(fields[11]).map_err(err(12))?,
+            v5: Self::split_codes(fields[12]).map_err(err(13))?,
+            shortcut: match fields[13] {
+                "NA" => None,
+                // The table's spelling of the space bar, since a space
+                // would end the field.
+                "SPACE" => Some(" ".to_string()),
+                shortcut => Some(shortcut.to_string()),
+            },
+            order: fields[14]
+                .parse()
+                .map_err(|_| ParseErrorKind::InvalidOrder(fields[14].to_string()))
+                .map_err(err(15))?,
+        })
+    }
+
+    /// Parses every data line of `txt`, yielding each line's result. Comment
+    /// and blank lines are skipped.
+    fn parse_lines(txt: &str) -> impl Iterator<Item = Result<Entry, ParseError>> + '_ {
         txt.split('\n')
-            .filter(|line| !(line.starts_with("# ") || line.is_empty()))
-            .map(|line| {
-                let fields = line.split(' ').collect::<Vec<&str>>();
-                Entry {
-                    traditional: fields.first().unwrap().chars().nth(0).unwrap(),
-                    simplified: fields.get(1).unwrap().chars().nth(0).unwrap(),
-                    chinese: *fields.get(2).unwrap() == "1",
-                    big5: *fields.get(3).unwrap() == "1",
-                    hkscs: *fields.get(4).unwrap() == "1",
-                    taiwanese: *fields.get(5).unwrap() == "1",
-                    kanji: *fields.get(6).unwrap() == "1",
-                    hiragana: *fields.get(7).unwrap() == "1",
-                    katakana: *fields.get(8).unwrap() == "1",
-                    punctuation: *fields.get(9).unwrap() == "1",
-                    misc: *fields.get(10).unwrap() == "1",
-                    v3: fields.get(11).unwrap().to_string(),
-                    v5: fields.get(12).unwrap().to_string(),
-                    code: "".to_string(),
-                    shortcut: fields.get(13).unwrap().to_string(),
-                    order: fields.get(14).unwrap().parse().unwrap(),
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.strip_suffix('\r').unwrap_or(line)))
+            .filter(|(_, line)| !(line.starts_with("# ") || line.is_empty()))
+            .map(|(line_no, line)| Self::parse_line(line, line_no))
+    }
+
+    /// Parses `table.txt` data, failing on the first malformed line.
+    pub fn to_entries(txt: &str, filter: &CongkitFilter) -> Result<Vec<Entry>, ParseError> {
+        let mut entries = Vec::new();
+        for entry in Self::parse_lines(txt) {
+            let entry = entry?;
+            if Self::apply_filters(&entry, filter) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Parses `table.txt` data, skipping malformed lines and returning their
+    /// errors as warnings alongside the entries that did parse.
+    pub fn to_entries_lenient(txt: &str, filter: &CongkitFilter) -> (Vec<Entry>, Vec<ParseError>) {
+        let mut entries = Vec::new();
+        let mut warnings = Vec::new();
+        for entry in Self::parse_lines(txt) {
+            match entry {
+                Ok(entry) if Self::apply_filters(&entry, filter) => entries.push(entry),
+                Ok(_) => {}
+                Err(err) => warnings.push(err),
+            }
+        }
+        (entries, warnings)
+    }
+
+    pub fn from_txt(
+        txt: &str,
+        version: CongkitVersion,
+        filter: CongkitFilter,
+    ) -> Result<Self, ParseError> {
+        let entries = Self::to_entries(txt, &filter)?;
+        Ok(Self::from_entry_vec(entries, version))
+    }
+
+    pub fn from_txt_lenient(
+        txt: &str,
+        version: CongkitVersion,
+        filter: CongkitFilter,
+    ) -> (Self, Vec<ParseError>) {
+        let (entries, warnings) = Self::to_entries_lenient(txt, &filter);
+        (Self::from_entry_vec(entries, version), warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WO: &str = "我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA 22308";
+
+    fn parse(line: &str) -> Result<Entry, ParseError> {
+        CongkitDB::parse_line(line, 7)
+    }
+
+    fn error(line: &str) -> (usize, ParseErrorKind) {
+        let err = parse(line).unwrap_err();
+        assert_eq!(err.line, 7);
+        (err.column, err.kind)
+    }
+
+    #[test]
+    fn parses_a_line() {
+        let entry = parse(WO).unwrap();
+        assert_eq!(entry.traditional, '我');
+        assert_eq!(entry.simplified, Some('我'));
+        assert!(entry.chinese && entry.big5 && entry.kanji && !entry.hkscs);
+        assert_eq!(entry.v3, ["hqi"]);
+        assert_eq!(entry.shortcut, None);
+        assert_eq!(entry.order, 22308);
+
+        let entry = parse("、 NA 0 0 0 0 0 0 0 1 0 NA NA , 1").unwrap();
+        assert_eq!(entry.simplified, None);
+        assert!(entry.v3.is_empty() && entry.v5.is_empty());
+        assert_eq!(entry.shortcut.as_deref(), Some(","));
+    }
+
+    #[test]
+    fn keeps_every_line_of_a_character() {
+        let txt = "‘ NA 0 0 0 0 0 0 0 1 0 yyybu yyybu NA 0\n\
+                   ‘ NA 0 0 0 0 0 0 0 1 0 zxcr za,zxcr NA 0\n";
+        let db = CongkitDB::from_txt(txt, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.get_entries(&'‘').len(), 2);
+        assert_eq!(db.get_code(&'‘').unwrap(), ["yyybu", "zxcr"]);
+        assert_eq!(
+            db.get_code_in(&'‘', CongkitVersion::V5).unwrap(),
+            ["yyybu", "za", "zxcr"]
+        );
+        assert_eq!(db.get_characters("yyybu").unwrap(), ['‘']);
+        assert_eq!(db.get_characters("zxcr").unwrap(), ['‘']);
+        assert_eq!(db.get_characters("*").unwrap(), ['‘']);
+    }
+
+    #[test]
+    fn either_version() {
+        let txt = "曰 曰 1 1 0 0 1 0 0 0 0 a,ax a,xa NA 90\n\
+                   日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                   月 月 1 1 0 0 1 0 0 0 0 b NA NA 50\n";
+        let entries = CongkitDB::to_entries(txt, &CongkitFilter::all()).unwrap();
+        assert_eq!(
+            entries[0].codes(CongkitVersion::Either).collect::<Vec<_>>(),
+            ["a", "ax", "xa"]
+        );
+
+        let mut db = CongkitDB::from_txt(txt, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(db.get_code(&'曰').unwrap(), ["a", "ax"]);
+        assert!(db.get_characters("xa").unwrap().is_empty());
+
+        db.set_version(CongkitVersion::Either);
+        assert_eq!(db.version(), CongkitVersion::Either);
+        assert_eq!(db.get_code(&'曰').unwrap(), ["a", "ax", "xa"]);
+        assert_eq!(db.get_characters("xa").unwrap(), ['曰']);
+        assert_eq!(db.get_characters("ax").unwrap(), ['曰']);
+        // 日 and 曰 share `a` in both versions, and each is listed once.
+        assert_eq!(db.get_characters("a").unwrap(), ['日', '曰']);
+        assert_eq!(db.get_code(&'月').unwrap(), ["b"]);
+
+        db.set_version(CongkitVersion::V5);
+        assert_eq!(db.get_code(&'曰').unwrap(), ["a", "xa"]);
+        assert_eq!(db.get_code(&'月').unwrap(), Vec::<CangjieCode>::new());
+        assert!(db.get_characters("ax").unwrap().is_empty());
+    }
+
+    #[test]
+    fn shortcuts() {
+        let txt = "、 NA 0 0 0 0 0 0 0 1 0 NA NA , 1\n\
+                   ， NA 0 0 0 0 0 0 0 1 0 NA NA , 0\n\
+                   \u{3000} NA 0 0 0 0 0 0 0 1 0 zxaa zxaa SPACE 0\n";
+        let db = CongkitDB::from_txt(txt, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(db.get_shortcut_characters(","), ['、', '，']);
+        assert_eq!(db.get_shortcut(&'，').as_deref(), Some(","));
+        assert_eq!(db.get_shortcut_characters(" "), ['\u{3000}']);
+        assert_eq!(db.get_shortcut(&'\u{3000}').as_deref(), Some(" "));
+        assert!(db.get_shortcut_characters("SPACE").is_empty());
+    }
+
+    #[test]
+    fn splits_code_lists() {
+        let entry = parse("曰 曰 1 1 0 0 1 0 0 0 0 a,ax a,xa NA 23060").unwrap();
+        assert_eq!(entry.v3, ["a", "ax"]);
+        assert_eq!(entry.v5, ["a", "xa"]);
+    }
+
+    #[test]
+    fn wrong_field_count() {
+        assert_eq!(
+            error("我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA"),
+            (
+                15,
+                ParseErrorKind::FieldCount {
+                    expected: 15,
+                    found: 14
                 }
-            })
-            .filter(|entry| Self::apply_filters(entry, filter))
-            .collect::<Vec<Entry>>()
+            )
+        );
+        assert_eq!(
+            error(&format!("{WO} 1")).1,
+            ParseErrorKind::FieldCount {
+                expected: 15,
+                found: 16
+            }
+        );
     }
 
-    pub fn from_txt(txt: &str, version: CongkitVersion, filter: CongkitFilter) -> Self {
-        let entries = Self::to_entries(txt, &filter);
-        Self::from_entry_vec(entries, version)
+    #[test]
+    fn empty_field() {
+        assert_eq!(
+            error("我 我 1 1  0 1 0 0 0 0 hqi hqi NA 22308"),
+            (5, ParseErrorKind::EmptyField)
+        );
     }
 
-    // pub fn new(version: CongkitVersion, filter: CongkitFilter) -> Self {
-    //     // filters should be applied to entries here, and then the hashmaps build from there.
-    //     Self {
-    //         ..Default::default()
-    //     }
-    // }
+    #[test]
+    fn bad_fields() {
+        assert_eq!(
+            error("我我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA 22308"),
+            (1, ParseErrorKind::NotACharacter("我我".to_string()))
+        );
+        assert_eq!(
+            error("我 我 1 2 0 0 1 0 0 0 0 hqi hqi NA 22308"),
+            (4, ParseErrorKind::NotABool("2".to_string()))
+        );
+        assert_eq!(
+            error("我 我 1 1 0 0 1 0 0 0 0 hqi hQi NA 22308"),
+            (13, ParseErrorKind::InvalidCode("hQi".to_string()))
+        );
+        assert_eq!(
+            error("我 我 1 1 0 0 1 0 0 0 0 hqi,abcdef hqi NA 22308"),
+            (12, ParseErrorKind::InvalidCode("abcdef".to_string()))
+        );
+        assert_eq!(
+            error("我 我 1 1 0 0 1 0 0 0 0 hqi hqi NA x"),
+            (15, ParseErrorKind::InvalidOrder("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn crlf_lines_and_comments() {
+        let txt = format!("# comment\r\n\r\n{WO}\r\n你 你 1 1 0 0 1 0 0 0 0 onf onf NA 22461\r\n");
+        let entries = CongkitDB::to_entries(&txt, &CongkitFilter::all()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].order, 22308);
+        assert_eq!(entries[1].order, 22461);
+    }
+
+    #[test]
+    fn lenient_parsing_collects_warnings() {
+        let txt = format!("{WO}\n我 我 1 1 0\n你 你 1 1 0 0 1 0 0 0 0 onf onf NA x\n");
+        assert_eq!(
+            CongkitDB::to_entries(&txt, &CongkitFilter::all())
+                .unwrap_err()
+                .line,
+            2
+        );
+        let (entries, warnings) = CongkitDB::to_entries_lenient(&txt, &CongkitFilter::all());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|warning| (warning.line, warning.column))
+                .collect::<Vec<_>>(),
+            vec![(2, 6), (3, 15)]
+        );
+    }
 }
@@ -0,0 +1,285 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use congkit::{
+    CongkitDB, CongkitFilter, CongkitVersion, DataWriter, EncodeFormat, MappedDB, UserDictionary,
+    MAPPED_MAGIC,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Parser)]
+#[command(about = "Look up and build Cangjie tables")]
+struct Cli {
+    /// A table.txt or .dat file to load. Defaults to the embedded table,
+    /// when built with `embed-full` or `embed-trimmed`.
+    #[arg(long, global = true)]
+    table: Option<PathBuf>,
+    /// Cangjie version to look codes up in.
+    #[arg(long = "version", value_name = "VERSION", global = true, value_enum, default_value_t = Version::V3)]
+    cangjie_version: Version,
+    /// Characters to load: `all`, `chinese` (the default), `japanese`, or a
+    /// comma-separated list of categories (e.g. `big5,hkscs`). A memory-mapped
+    /// table keeps the filter it was built with.
+    #[arg(long, global = true, value_parser = parse_filter)]
+    filter: Option<CongkitFilter>,
+    /// A user dictionary to layer over the table.
+    #[arg(long, global = true)]
+    dictionary: Option<PathBuf>,
+    /// Print JSON instead of plain text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the codes of each character of the given text.
+    Encode {
+        text: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Format::Codes)]
+        format: Format,
+    },
+    /// Print the text spelled by a sequence of codes.
+    Decode { codes: Vec<String> },
+    /// Print the characters matching each wildcard pattern.
+    Search { patterns: Vec<String> },
+    /// Compile a table.txt into a .dat file.
+    Build {
+        input: PathBuf,
+        output: PathBuf,
+        /// Skip malformed lines instead of failing on the first one.
+        #[arg(long)]
+        lenient: bool,
+        /// Write the memory-mapped layout, which `search` reads in place
+        /// when given as `--table`. It is told apart by its magic, so any
+        /// extension works.
+        #[arg(long)]
+        mapped: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Version {
+    V3,
+    V5,
+    Either,
+}
+
+impl From<Version> for CongkitVersion {
+    fn from(version: Version) -> Self {
+        match version {
+            Version::V3 => CongkitVersion::V3,
+            Version::V5 => CongkitVersion::V5,
+            Version::Either => CongkitVersion::Either,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Space-separated codes.
+    Codes,
+    /// Space-separated radicals.
+    Radicals,
+    /// The text with each character's code after it.
+    Annotated,
+}
+
+impl From<Format> for EncodeFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Codes => EncodeFormat::Codes,
+            Format::Radicals => EncodeFormat::Radicals,
+            Format::Annotated => EncodeFormat::Annotated,
+        }
+    }
+}
+
+fn parse_filter(s: &str) -> Result<CongkitFilter, String> {
+    match s {
+        "all" => return Ok(CongkitFilter::all()),
+        "chinese" => return Ok(CongkitFilter::chinese()),
+        "japanese" => return Ok(CongkitFilter::japanese()),
+        _ => {}
+    }
+    let mut filter = CongkitFilter {
+        chinese: false,
+        big5: false,
+        hkscs: false,
+        taiwanese: false,
+        kanji: false,
+        hiragana: false,
+        katakana: false,
+        punctuation: false,
+        misc: false,
+    };
+    for category in s.split(',') {
+        let flag = match category {
+            "chinese" => &mut filter.chinese,
+            "big5" => &mut filter.big5,
+            "hkscs" => &mut filter.hkscs,
+            "taiwanese" => &mut filter.taiwanese,
+            "kanji" => &mut filter.kanji,
+            "hiragana" => &mut filter.hiragana,
+            "katakana" => &mut filter.katakana,
+            "punctuation" => &mut filter.punctuation,
+            "misc" => &mut filter.misc,
+            _ => return Err(format!("unknown category {category:?}")),
+        };
+        *flag = true;
+    }
+    Ok(filter)
+}
+
+fn filter(cli: &Cli) -> CongkitFilter {
+    cli.filter.clone().unwrap_or_else(CongkitFilter::chinese)
+}
+
+fn load(cli: &Cli) -> Result<CongkitDB> {
+    let mut db = load_table(cli)?;
+    if let Some(path) = &cli.dictionary {
+        db.set_user_dictionary(UserDictionary::load(path)?);
+    }
+    Ok(db)
+}
+
+/// The `--table` as a memory-mapped table, if it is one and nothing needs
+/// the full database. Mapped tables store no categories to filter on, so a
+/// `--filter` other than the one the table was built with is an error.
+fn load_mapped(cli: &Cli) -> Result<Option<MappedDB>> {
+    match &cli.table {
+        Some(path) if is_mapped(path) && cli.dictionary.is_none() => {
+            let db = MappedDB::open(path, cli.cangjie_version.into())?;
+            let built_with = db.header()?.filter;
+            if cli
+                .filter
+                .as_ref()
+                .is_some_and(|filter| *filter != built_with)
+            {
+                anyhow::bail!(
+                    "{} was built with --filter {:?} and cannot be filtered further; \
+                     rebuild it with `build --mapped --filter ...`",
+                    path.display(),
+                    built_with
+                );
+            }
+            Ok(Some(db))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether the file at `path` starts with [`MAPPED_MAGIC`], whatever its
+/// extension.
+fn is_mapped(path: &Path) -> bool {
+    let mut magic = [0; MAPPED_MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| magic == MAPPED_MAGIC)
+}
+
+fn load_table(cli: &Cli) -> Result<CongkitDB> {
+    let version = cli.cangjie_version.into();
+    let filter = filter(cli);
+    match &cli.table {
+        Some(path) if is_mapped(path) => anyhow::bail!(
+            "{} is a memory-mapped table, which only `search` without --dictionary can read",
+            path.display()
+        ),
+        Some(path) if path.extension().is_some_and(|ext| ext == "txt") => Ok(CongkitDB::from_txt(
+            &fs::read_to_string(path)?,
+            version,
+            filter,
+        )?),
+        Some(path) => Ok(CongkitDB::from_data(&fs::read(path)?, version, filter)?),
+        #[cfg(any(feature = "embed-full", feature = "embed-trimmed"))]
+        None => Ok(CongkitDB::new(version, filter)?),
+        #[cfg(not(any(feature = "embed-full", feature = "embed-trimmed")))]
+        None => anyhow::bail!("no --table given, and no table is embedded in this build"),
+    }
+}
+
+#[derive(Serialize)]
+struct Built {
+    entries: usize,
+    warnings: Vec<String>,
+}
+
+fn print<T: Serialize>(json: bool, value: &T, text: impl FnOnce() -> String) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        println!("{}", text());
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match &cli.command {
+        Command::Encode { text, format } => {
+            let db = load(&cli)?;
+            let text = text.join(" ");
+            print(cli.json, &db.encode(&text), || {
+                db.encode_to_string(&text, (*format).into())
+            })
+        }
+        Command::Decode { codes } => {
+            let db = load(&cli)?;
+            let decoded = db.decode(&codes.join(" "));
+            print(cli.json, &decoded, || decoded.text.clone())
+        }
+        Command::Search { patterns } => {
+            let found = match load_mapped(&cli)? {
+                Some(db) => patterns
+                    .iter()
+                    .map(|p| Ok((p.clone(), db.get_characters(p)?)))
+                    .collect::<Result<HashMap<String, Vec<char>>>>()?,
+                None => load(&cli)?.get_chars_mult(patterns.clone())?,
+            };
+            print(cli.json, &found, || {
+                patterns
+                    .iter()
+                    .map(|p| format!("{}\t{}", p, found[p].iter().collect::<String>()))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            })
+        }
+        Command::Build {
+            input,
+            output,
+            lenient,
+            mapped,
+        } => {
+            let txt = fs::read_to_string(input)?;
+            let filter = filter(&cli);
+            let (entries, warnings) = if *lenient {
+                CongkitDB::to_entries_lenient(&txt, &filter)
+            } else {
+                (CongkitDB::to_entries(&txt, &filter)?, Vec::new())
+            };
+            let writer = DataWriter::new(&txt, filter);
+            if *mapped {
+                fs::write(output, writer.write_mapped(&entries))?;
+            } else {
+                fs::write(output, writer.write(&entries))?;
+            }
+            let built = Built {
+                entries: entries.len(),
+                warnings: warnings.iter().map(|w| w.to_string()).collect(),
+            };
+            for warning in built.warnings.iter().filter(|_| !cli.json) {
+                eprintln!("warning: {warning}");
+            }
+            print(cli.json, &built, || {
+                format!("wrote {} entries to {}", built.entries, output.display())
+            })
+        }
+    }
+}
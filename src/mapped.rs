@@ -0,0 +1,485 @@
+//! A table laid out as fixed-size records that can be memory-mapped and
+//! queried in place, for tools that run a few lookups and exit.
+//!
+//! After the common container prefix (see [`DataWriter`]) with [`MAPPED_MAGIC`],
+//! the body is three little-endian `u32` counts followed by three arrays:
+//!
+//! - characters, 16 bytes each, sorted by character: the character, its
+//!   order, the index of its first link, and its V3 and V5 code counts
+//!   (`u16` each);
+//! - codes, 16 bytes each, sorted by version (V3 then V5), code, rank and
+//!   character: the packed [`CangjieCode`], the version (0 for V3, 1 for
+//!   V5), 3 bytes of padding, the character and its order;
+//! - links, 4 bytes each: for every character, the indices in the code
+//!   array of its V3 codes, then its V5 codes, then its codes for
+//!   [`CongkitVersion::Either`], which run up to the next character's first
+//!   link.
+
+use crate::format::{split, take_u32};
+use crate::{
+    rank, ranked_characters, CangjieCode, CongkitVersion, DataError, DataHeader, DataWriter, Entry,
+    Pattern, PatternError,
+};
+use memmap2::Mmap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+/// The first bytes of every memory-mapped table.
+pub const MAPPED_MAGIC: [u8; 8] = *b"CONGKMAP";
+
+const RECORD_LEN: usize = 16;
+const LINK_LEN: usize = 4;
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+}
+
+/// The first index in `0..len` for which `pred` is false, given that `pred`
+/// is true for a prefix of the range.
+fn partition_point(len: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = (low + high) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+const VERSIONS: [CongkitVersion; 3] = [
+    CongkitVersion::V3,
+    CongkitVersion::V5,
+    CongkitVersion::Either,
+];
+
+/// One character's merged order and its codes in each of [`VERSIONS`],
+/// paired with the version byte of the code record they link to.
+struct CharRecord {
+    order: i32,
+    links: [Vec<(u8, CangjieCode)>; 3],
+}
+
+impl DataWriter {
+    /// Writes entries in the memory-mapped layout read by [`MappedDB`].
+    /// Entries for the same character are merged into one character record
+    /// with the highest of their orders.
+    pub fn write_mapped(&self, entries: &[Entry]) -> Vec<u8> {
+        let mut chars: BTreeMap<char, CharRecord> = BTreeMap::new();
+        for entry in entries {
+            let record = chars.entry(entry.traditional).or_insert(CharRecord {
+                order: entry.order,
+                links: Default::default(),
+            });
+            record.order = record.order.max(entry.order);
+            for (links, version) in record.links.iter_mut().zip(VERSIONS) {
+                for code in entry.codes(version) {
+                    let byte = u8::from(!entry.v3.contains(&code));
+                    if !links.iter().any(|(_, c)| *c == code) {
+                        links.push((byte, code));
+                    }
+                }
+            }
+        }
+
+        // Each code keeps the order of the entry it came from, so that
+        // ranking matches `CongkitDB` when a character's entries differ.
+        let mut codes = entries
+            .iter()
+            .flat_map(|entry| {
+                let v3 = entry.v3.iter().map(|code| (0u8, *code));
+                let v5 = entry.v5.iter().map(|code| (1u8, *code));
+                v3.chain(v5)
+                    .map(|(version, code)| (version, code, rank(entry.order), entry.traditional))
+            })
+            .collect::<Vec<_>>();
+        codes.sort();
+        let mut seen = HashSet::new();
+        codes.retain(|(version, code, _, c)| seen.insert((*version, *code, *c)));
+        let positions = codes
+            .iter()
+            .enumerate()
+            .map(|(i, (version, code, _, c))| ((*c, *version, *code), i as u32))
+            .collect::<HashMap<_, _>>();
+
+        let mut data = self.container(&MAPPED_MAGIC, entries);
+        let mut links = Vec::new();
+        data.extend_from_slice(&(chars.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(codes.len() as u32).to_le_bytes());
+        let link_count = chars
+            .values()
+            .flat_map(|record| &record.links)
+            .map(Vec::len)
+            .sum::<usize>();
+        data.extend_from_slice(&(link_count as u32).to_le_bytes());
+        for (c, record) in &chars {
+            data.extend_from_slice(&(*c as u32).to_le_bytes());
+            data.extend_from_slice(&record.order.to_le_bytes());
+            data.extend_from_slice(&((links.len() / LINK_LEN) as u32).to_le_bytes());
+            data.extend_from_slice(&(record.links[0].len() as u16).to_le_bytes());
+            data.extend_from_slice(&(record.links[1].len() as u16).to_le_bytes());
+            for (version, code) in record.links.iter().flatten() {
+                links.extend_from_slice(&positions[&(*c, *version, *code)].to_le_bytes());
+            }
+        }
+        for (version, code, order, c) in &codes {
+            data.extend_from_slice(&code.to_u32().to_le_bytes());
+            data.extend_from_slice(&[*version, 0, 0, 0]);
+            data.extend_from_slice(&(*c as u32).to_le_bytes());
+            data.extend_from_slice(&order.0.to_le_bytes());
+        }
+        data.extend_from_slice(&links);
+        data
+    }
+}
+
+/// A table memory-mapped from a file written by
+/// [`DataWriter::write_mapped`], answering lookups by character and by code
+/// straight from the mapped records.
+///
+/// Opening one costs a header check and a pass over the links, rather than
+/// decoding and indexing every entry. Only codes and order are stored:
+/// there are no simplified forms, shortcuts, categories to filter on or user
+/// dictionaries, and no completion or Quick lookups, which all need the
+/// decoded entries and indexes that [`CongkitDB`](crate::CongkitDB) builds.
+/// The filter is fixed when the file is written.
+#[derive(Debug)]
+pub struct MappedDB {
+    map: Mmap,
+    /// Where each array starts in `map`.
+    chars: usize,
+    codes: usize,
+    links: usize,
+    char_count: usize,
+    code_count: usize,
+    link_count: usize,
+    version: CongkitVersion,
+}
+
+impl MappedDB {
+    pub fn open(path: impl AsRef<Path>, version: CongkitVersion) -> Result<Self, DataError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, and the file is expected to stay
+        // unchanged while open, like any other memory-mapped data file.
+        let map = unsafe { Mmap::map(&file)? };
+        let (header, body) = split(&map, &MAPPED_MAGIC)?;
+        if !header.has_version(version) {
+            return Err(DataError::MissingVersion {
+                requested: version,
+                available: header.versions,
+            });
+        }
+        let chars = map.len() - body.len() + 12;
+        let (char_count, rest) = take_u32(body)?;
+        let (code_count, rest) = take_u32(rest)?;
+        let (link_count, rest) = take_u32(rest)?;
+        let (char_count, code_count, link_count) = (
+            char_count as usize,
+            code_count as usize,
+            link_count as usize,
+        );
+        let codes = chars + char_count * RECORD_LEN;
+        let links = codes + code_count * RECORD_LEN;
+        if rest.len() != (char_count + code_count) * RECORD_LEN + link_count * LINK_LEN {
+            return Err(DataError::Truncated);
+        }
+        let db = Self {
+            map,
+            chars,
+            codes,
+            links,
+            char_count,
+            code_count,
+            link_count,
+            version,
+        };
+        db.check_links()?;
+        Ok(db)
+    }
+
+    /// Checks that every character's links lie within the link array, in
+    /// order, and that every link points at a code record, so that lookups
+    /// can index the arrays directly.
+    fn check_links(&self) -> Result<(), DataError> {
+        let mut end = 0;
+        for i in 0..self.char_count {
+            let record = self.char_record(i);
+            let first = read_u32(record, 8) as usize;
+            let own = read_u16(record, 12) as usize + read_u16(record, 14) as usize;
+            if first < end || first + own > self.link_count {
+                return Err(DataError::BadRecord {
+                    array: "character",
+                    index: i,
+                });
+            }
+            end = first + own;
+        }
+        for link in 0..self.link_count {
+            if read_u32(&self.map, self.links + link * LINK_LEN) as usize >= self.code_count {
+                return Err(DataError::BadRecord {
+                    array: "link",
+                    index: link,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The header the file was written with.
+    pub fn header(&self) -> Result<DataHeader, DataError> {
+        Ok(split(&self.map, &MAPPED_MAGIC)?.0)
+    }
+
+    pub fn version(&self) -> CongkitVersion {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: CongkitVersion) {
+        self.version = version;
+    }
+
+    /// Number of characters.
+    pub fn len(&self) -> usize {
+        self.char_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.char_count == 0
+    }
+
+    fn char_record(&self, i: usize) -> &[u8] {
+        let at = self.chars + i * RECORD_LEN;
+        &self.map[at..at + RECORD_LEN]
+    }
+
+    fn code_record(&self, i: usize) -> &[u8] {
+        let at = self.codes + i * RECORD_LEN;
+        &self.map[at..at + RECORD_LEN]
+    }
+
+    /// The index of the record for `character`.
+    fn find_char(&self, character: &char) -> Option<usize> {
+        let target = *character as u32;
+        let i = partition_point(self.char_count, |i| {
+            read_u32(self.char_record(i), 0) < target
+        });
+        (i < self.char_count && read_u32(self.char_record(i), 0) == target).then_some(i)
+    }
+
+    pub fn contains(&self, character: &char) -> bool {
+        self.find_char(character).is_some()
+    }
+
+    /// The order of `character`, higher first; see [`Entry::order`].
+    pub fn get_order(&self, character: &char) -> Option<i32> {
+        let i = self.find_char(character)?;
+        Some(read_u32(self.char_record(i), 4) as i32)
+    }
+
+    /// Returns the codes of `character` in the active version.
+    pub fn get_code(&self, character: &char) -> Option<Vec<CangjieCode>> {
+        self.get_code_in(character, self.version)
+    }
+
+    /// Returns the codes of `character` in `version`; for
+    /// [`CongkitVersion::Either`], its V3 codes followed by any different V5
+    /// codes.
+    pub fn get_code_in(
+        &self,
+        character: &char,
+        version: CongkitVersion,
+    ) -> Option<Vec<CangjieCode>> {
+        let i = self.find_char(character)?;
+        let record = self.char_record(i);
+        let first = read_u32(record, 8) as usize;
+        let (v3, v5) = (read_u16(record, 12) as usize, read_u16(record, 14) as usize);
+        let links = match version {
+            CongkitVersion::V3 => first..first + v3,
+            CongkitVersion::V5 => first + v3..first + v3 + v5,
+            CongkitVersion::Either => {
+                let end = if i + 1 < self.char_count {
+                    read_u32(self.char_record(i + 1), 8) as usize
+                } else {
+                    self.link_count
+                };
+                first + v3 + v5..end
+            }
+        };
+        Some(
+            links
+                .filter_map(|link| {
+                    let code = read_u32(&self.map, self.links + link * LINK_LEN) as usize;
+                    Self::code(self.code_record(code))
+                })
+                .collect(),
+        )
+    }
+
+    fn code(record: &[u8]) -> Option<CangjieCode> {
+        CangjieCode::from_u32(read_u32(record, 0))
+    }
+
+    /// The range of code records in `version` (V3 or V5) whose code starts
+    /// with `prefix`.
+    fn prefix_range(&self, version: u8, prefix: &CangjieCode) -> Range<usize> {
+        let key = |i: usize| (self.code_record(i)[4], read_u32(self.code_record(i), 0));
+        let start = partition_point(self.code_count, |i| key(i) < (version, prefix.to_u32()));
+        let end = partition_point(self.code_count, |i| {
+            let (v, code) = key(i);
+            v < version
+                || v == version
+                    && (code < prefix.to_u32()
+                        || Self::code(self.code_record(i))
+                            .is_some_and(|code| code.starts_with(prefix)))
+        });
+        start..end
+    }
+
+    /// Returns the characters with a code in the active version matching
+    /// `pattern`, ranked by order, as
+    /// [`CongkitDB::get_characters`](crate::CongkitDB::get_characters) does.
+    pub fn get_characters(&self, pattern: &str) -> Result<Vec<char>, PatternError> {
+        self.get_characters_in(pattern, self.version)
+    }
+
+    pub fn get_characters_in(
+        &self,
+        pattern: &str,
+        version: CongkitVersion,
+    ) -> Result<Vec<char>, PatternError> {
+        let pattern = Pattern::parse(pattern)?;
+        let prefix = pattern.literal_prefix();
+        let versions: &[u8] = match version {
+            CongkitVersion::V3 => &[0],
+            CongkitVersion::V5 => &[1],
+            CongkitVersion::Either => &[0, 1],
+        };
+        let hits = versions
+            .iter()
+            .flat_map(|v| self.prefix_range(*v, &prefix))
+            .map(|i| self.code_record(i))
+            .filter(|record| Self::code(record).is_some_and(|code| pattern.matches_code(&code)))
+            .filter_map(|record| {
+                let character = char::from_u32(read_u32(record, 8))?;
+                Some((rank(read_u32(record, 12) as i32), character))
+            })
+            .collect::<Vec<_>>();
+        Ok(ranked_characters(hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CongkitDB, CongkitFilter};
+
+    /// 昌 is on two lines with different orders, and 曰 has a V5 code of its
+    /// own.
+    const TXT: &str = "日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a a,xa NA 90\n\
+                       昌 昌 1 1 0 0 1 0 0 0 0 aa aa NA 80\n\
+                       明 明 1 1 0 0 1 0 0 0 0 ab b NA 70\n\
+                       昌 昌 1 1 0 0 1 0 0 0 0 ax ax NA 95\n";
+
+    fn data() -> Vec<u8> {
+        let filter = CongkitFilter::all();
+        let entries = CongkitDB::to_entries(TXT, &filter).unwrap();
+        DataWriter::new(TXT, filter).write_mapped(&entries)
+    }
+
+    /// Opens `data` from a file named after the calling test, which is
+    /// removed again once mapped.
+    fn open(name: &str, data: &[u8], version: CongkitVersion) -> Result<MappedDB, DataError> {
+        let path =
+            std::env::temp_dir().join(format!("congkit-mapped-{}-{name}.map", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let db = MappedDB::open(&path, version);
+        std::fs::remove_file(&path).unwrap();
+        db
+    }
+
+    #[test]
+    fn matches_congkit_db() {
+        let db = CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        for version in VERSIONS {
+            let mapped = open("round-trip", &data(), version).unwrap();
+            assert_eq!(mapped.len(), 4);
+            for pattern in ["a", "a*", "*a", "a?", "x*", "*", "b", "z"] {
+                assert_eq!(
+                    mapped.get_characters(pattern).unwrap(),
+                    db.get_characters_in(pattern, version).unwrap(),
+                    "{version:?} {pattern}"
+                );
+            }
+            for c in ['日', '曰', '昌', '明', '月'] {
+                assert_eq!(mapped.get_code(&c), db.get_code_in(&c, version), "{c}");
+            }
+        }
+    }
+
+    #[test]
+    fn duplicated_characters() {
+        let mapped = open("duplicated", &data(), CongkitVersion::V3).unwrap();
+        assert_eq!(mapped.get_order(&'昌'), Some(95));
+        assert_eq!(mapped.get_code(&'昌').unwrap(), ["aa", "ax"]);
+        // Each code keeps the order of its own line.
+        assert_eq!(
+            mapped.get_characters("a*").unwrap(),
+            ['日', '昌', '曰', '明']
+        );
+        assert_eq!(mapped.get_characters("a?").unwrap(), ['昌', '明']);
+    }
+
+    #[test]
+    fn bad_records() {
+        let data = data();
+        let db = open("bad-records-base", &data, CongkitVersion::V3).unwrap();
+
+        let mut bad_link = data.clone();
+        let at = db.links + LINK_LEN;
+        bad_link[at..at + 4].copy_from_slice(&(db.code_count as u32).to_le_bytes());
+        assert!(matches!(
+            open("bad-link", &bad_link, CongkitVersion::V3),
+            Err(DataError::BadRecord {
+                array: "link",
+                index: 1
+            })
+        ));
+
+        let mut bad_char = data.clone();
+        let at = db.chars + RECORD_LEN * 2 + 8;
+        bad_char[at..at + 4].copy_from_slice(&(db.link_count as u32).to_le_bytes());
+        assert!(matches!(
+            open("bad-char", &bad_char, CongkitVersion::V3),
+            Err(DataError::BadRecord {
+                array: "character",
+                index: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = data();
+        assert!(matches!(
+            open("truncated", &data[..data.len() - 1], CongkitVersion::V3),
+            Err(DataError::Truncated)
+        ));
+        assert!(matches!(
+            open(
+                "short-body",
+                &data[..data.len() - LINK_LEN * 3 - 10],
+                CongkitVersion::V3
+            ),
+            Err(DataError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,396 @@
+//! User dictionaries layered over the loaded table at runtime.
+
+use crate::{CangjieCode, CongkitDB, Entry, LoadError, ParseError, ParseErrorKind};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::{fs, path::Path};
+
+/// What a [`UserDictionary`] does to one character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Override {
+    /// Replaces the character's codes (in every version) and/or its order.
+    /// A character missing from the table is added, as long as it is given
+    /// codes.
+    Set {
+        codes: Option<Vec<CangjieCode>>,
+        order: Option<i32>,
+    },
+    /// Hides the character from every lookup.
+    Hide,
+}
+
+impl Override {
+    /// The entries for `character` once this override is applied to its
+    /// table entries `base`.
+    fn apply(&self, character: char, base: &[Entry]) -> Vec<Entry> {
+        let Override::Set { codes, order } = self else {
+            return Vec::new();
+        };
+        let mut entries = match codes {
+            // Nothing to add: the character could not be typed.
+            Some(codes) if codes.is_empty() && base.is_empty() => Vec::new(),
+            Some(codes) => {
+                let mut entry = base.first().cloned().unwrap_or(Entry {
+                    traditional: character,
+                    simplified: None,
+                    chinese: false,
+                    big5: false,
+                    hkscs: false,
+                    taiwanese: false,
+                    kanji: false,
+                    hiragana: false,
+                    katakana: false,
+                    punctuation: false,
+                    misc: false,
+                    v3: Vec::new(),
+                    v5: Vec::new(),
+                    shortcut: None,
+                    order: 0,
+                });
+                entry.v3 = codes.clone();
+                entry.v5 = codes.clone();
+                vec![entry]
+            }
+            None => base.to_vec(),
+        };
+        if let Some(order) = order {
+            entries.iter_mut().for_each(|entry| entry.order = *order);
+        }
+        entries
+    }
+}
+
+/// Per-character additions, overrides and hidden characters applied on top
+/// of a table with [`CongkitDB::set_user_dictionary`].
+///
+/// The file format is plain text, one directive per line:
+///
+/// ```text
+/// # Lines starting with '#' are comments.
+/// add 𠀀 abc,abd 100
+/// codes 字 jnd
+/// order 字 5000
+/// hide 屄
+/// ```
+///
+/// `add` sets both codes and order; `codes` and `order` set one of them.
+/// Lines apply in order: `add`, `codes` and `order` merge into earlier lines
+/// for the same character, while `hide` replaces them. An `add`, `codes` or
+/// `order` line after `hide` shows the character again, starting over from
+/// its table entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserDictionary {
+    overrides: BTreeMap<char, Override>,
+}
+
+impl UserDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    pub fn get(&self, character: &char) -> Option<&Override> {
+        self.overrides.get(character)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Override)> {
+        self.overrides.iter().map(|(c, o)| (*c, o))
+    }
+
+    fn set(&mut self, character: char) -> (&mut Option<Vec<CangjieCode>>, &mut Option<i32>) {
+        let entry = self.overrides.entry(character).or_insert(Override::Set {
+            codes: None,
+            order: None,
+        });
+        if *entry == Override::Hide {
+            *entry = Override::Set {
+                codes: None,
+                order: None,
+            };
+        }
+        match entry {
+            Override::Set { codes, order } => (codes, order),
+            Override::Hide => unreachable!(),
+        }
+    }
+
+    /// Adds `character` with `codes` and `order`, or replaces both for a
+    /// character already in the table. A character missing from the table
+    /// is only added if `codes` is not empty.
+    pub fn add(&mut self, character: char, codes: Vec<CangjieCode>, order: i32) {
+        self.set_codes(character, codes);
+        self.set_order(character, order);
+    }
+
+    /// Replaces the codes of `character` in every version. With no codes,
+    /// the character stays in the table but cannot be typed. A hidden
+    /// character is shown again.
+    pub fn set_codes(&mut self, character: char, codes: Vec<CangjieCode>) {
+        *self.set(character).0 = Some(codes);
+    }
+
+    /// Replaces the order of `character`. A hidden character is shown again,
+    /// with its table codes.
+    pub fn set_order(&mut self, character: char, order: i32) {
+        *self.set(character).1 = Some(order);
+    }
+
+    pub fn hide(&mut self, character: char) {
+        self.overrides.insert(character, Override::Hide);
+    }
+
+    /// Drops whatever the dictionary does to `character`, so the table's
+    /// entries apply again.
+    pub fn remove(&mut self, character: &char) -> Option<Override> {
+        self.overrides.remove(character)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+/// The inverse of [`CongkitDB::split_codes`], writing `NA` for no codes as
+/// `table.txt` does.
+fn join_codes(codes: &[CangjieCode]) -> String {
+    if codes.is_empty() {
+        return "NA".to_string();
+    }
+    codes
+        .iter()
+        .map(CangjieCode::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+impl fmt::Display for UserDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (character, o) in self.iter() {
+            match o {
+                Override::Set {
+                    codes: Some(codes),
+                    order: Some(order),
+                } => writeln!(f, "add {} {} {}", character, join_codes(codes), order)?,
+                Override::Set {
+                    codes: Some(codes),
+                    order: None,
+                } => writeln!(f, "codes {} {}", character, join_codes(codes))?,
+                Override::Set {
+                    codes: None,
+                    order: Some(order),
+                } => writeln!(f, "order {} {}", character, order)?,
+                Override::Set {
+                    codes: None,
+                    order: None,
+                } => {}
+                Override::Hide => writeln!(f, "hide {}", character)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl UserDictionary {
+    fn parse_line(&mut self, fields: &[&str], line: usize) -> Result<(), ParseError> {
+        let err = |column: usize| ParseError::at(line, column);
+        let character =
+            |column: usize| CongkitDB::parse_char(fields[column - 1]).map_err(err(column));
+        let codes = |column: usize| CongkitDB::split_codes(fields[column - 1]).map_err(err(column));
+        let order = |column: usize| {
+            let field = fields[column - 1];
+            field
+                .parse::<i32>()
+                .map_err(|_| err(column)(ParseErrorKind::InvalidOrder(field.to_string())))
+        };
+        let expected = match fields.first() {
+            Some(&"add") => 4,
+            Some(&("codes" | "order")) => 3,
+            Some(&"hide") => 2,
+            Some(directive) => {
+                return Err(err(1)(ParseErrorKind::UnknownDirective(
+                    directive.to_string(),
+                )))
+            }
+            None => return Ok(()),
+        };
+        if fields.len() != expected {
+            return Err(err(fields.len().min(expected) + 1)(
+                ParseErrorKind::FieldCount {
+                    expected,
+                    found: fields.len(),
+                },
+            ));
+        }
+        match fields[0] {
+            "add" => self.add(character(2)?, codes(3)?, order(4)?),
+            "codes" => self.set_codes(character(2)?, codes(3)?),
+            "order" => self.set_order(character(2)?, order(3)?),
+            _ => self.hide(character(2)?),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for UserDictionary {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dictionary = Self::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            dictionary.parse_line(&fields, i + 1)?;
+        }
+        Ok(dictionary)
+    }
+}
+
+impl CongkitDB {
+    /// Layers `dictionary` over the table, replacing any previous one.
+    /// Every lookup, index and conversion sees the merged entries.
+    pub fn set_user_dictionary(&mut self, dictionary: UserDictionary) {
+        self.restore_base();
+        for (character, o) in dictionary.iter() {
+            let base = self.entries.remove(&character).unwrap_or_default();
+            let entries = o.apply(character, &base);
+            if !entries.is_empty() {
+                self.entries.insert(character, entries);
+            }
+            self.base.insert(character, base);
+        }
+        self.user_dictionary = Some(dictionary);
+        self.rebuild();
+    }
+
+    /// Removes the user dictionary, restoring the table's own entries.
+    pub fn take_user_dictionary(&mut self) -> Option<UserDictionary> {
+        self.restore_base();
+        self.rebuild();
+        self.user_dictionary.take()
+    }
+
+    pub fn user_dictionary(&self) -> Option<&UserDictionary> {
+        self.user_dictionary.as_ref()
+    }
+
+    /// Changes the user dictionary (starting from an empty one if there is
+    /// none) and re-applies it.
+    pub fn edit_user_dictionary(&mut self, edit: impl FnOnce(&mut UserDictionary)) {
+        let mut dictionary = self.user_dictionary.take().unwrap_or_default();
+        edit(&mut dictionary);
+        self.set_user_dictionary(dictionary);
+    }
+
+    /// Puts back the table entries the user dictionary replaced.
+    fn restore_base(&mut self) {
+        for (character, base) in self.base.drain() {
+            if base.is_empty() {
+                self.entries.remove(&character);
+            } else {
+                self.entries.insert(character, base);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Override, UserDictionary};
+    use crate::error::assert_parse_errors;
+    use crate::{CongkitDB, CongkitFilter, CongkitVersion, ParseError, ParseErrorKind};
+
+    const TXT: &str = "日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a a NA 90\n\
+                       明 明 1 1 0 0 1 0 0 0 0 ab ab NA 70\n";
+
+    #[test]
+    fn layers_over_the_table() {
+        let mut db = CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        let dictionary = "add 𠀀 a 95\nhide 曰\ncodes 明 a,ab\nadd 𠀁 NA 5\n"
+            .parse()
+            .unwrap();
+        db.set_user_dictionary(dictionary);
+        assert_eq!(db.get_characters("a").unwrap(), ['日', '𠀀', '明']);
+        assert_eq!(db.get_code(&'曰'), None);
+        assert_eq!(db.get_code(&'明').unwrap(), ["a", "ab"]);
+        // Not added, as it was given no codes.
+        assert_eq!(db.get_entry(&'𠀁'), None);
+        assert_eq!(db.len(), 3);
+
+        db.edit_user_dictionary(|dictionary| {
+            dictionary.remove(&'曰');
+        });
+        assert_eq!(db.get_characters("a").unwrap(), ['日', '𠀀', '曰', '明']);
+
+        assert!(db.take_user_dictionary().is_some());
+        assert_eq!(db.get_characters("a").unwrap(), ['日', '曰']);
+        assert_eq!(db.get_code(&'明').unwrap(), ["ab"]);
+        assert_eq!(db.get_code(&'𠀀'), None);
+        assert_eq!(db.len(), 3);
+    }
+
+    #[test]
+    fn later_lines_merge_and_unhide() {
+        let dictionary: UserDictionary = "codes 字 jnd\norder 字 5000\nhide 屄\norder 屄 1\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            dictionary.get(&'字'),
+            Some(&Override::Set {
+                codes: Some(vec!["jnd".parse().unwrap()]),
+                order: Some(5000),
+            })
+        );
+        assert_eq!(
+            dictionary.get(&'屄'),
+            Some(&Override::Set {
+                codes: None,
+                order: Some(1),
+            })
+        );
+        assert_eq!(dictionary.to_string(), "add 字 jnd 5000\norder 屄 1\n");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_parse_errors::<UserDictionary>(&[
+            (
+                "# comment\nshow 字",
+                ParseError::at(2, 1)(ParseErrorKind::UnknownDirective("show".to_string())),
+            ),
+            (
+                "add 字 jnd",
+                ParseError::at(1, 4)(ParseErrorKind::FieldCount {
+                    expected: 4,
+                    found: 3,
+                }),
+            ),
+            (
+                "hide 字\norder 字 high",
+                ParseError::at(2, 3)(ParseErrorKind::InvalidOrder("high".to_string())),
+            ),
+            (
+                "hide 字字",
+                ParseError::at(1, 2)(ParseErrorKind::NotACharacter("字字".to_string())),
+            ),
+            (
+                "codes 字 jQd",
+                ParseError::at(1, 3)(ParseErrorKind::InvalidCode("jQd".to_string())),
+            ),
+        ]);
+    }
+}
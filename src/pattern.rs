@@ -0,0 +1,266 @@
+//! Wildcard patterns over Cangjie codes.
+//!
+//! A pattern is a sequence of:
+//!
+//! - `a`–`z`: a literal Cangjie key,
+//! - `?`: exactly one key,
+//! - `*`: any run of keys. By default the run may be empty, so `*hqi` also
+//!   matches `hqi` itself; [`PatternOptions::star_matches_empty`] switches to
+//!   one-or-more.
+//!
+//! Any other character is rejected, as is a pattern whose fixed part (its keys
+//! and `?`s) is longer than a Cangjie code can be.
+
+use crate::{CangjieCode, CongkitDB};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PatternError {
+    #[error("pattern is empty")]
+    Empty,
+    #[error("invalid character {character:?} at position {position}")]
+    InvalidCharacter { position: usize, character: char },
+    #[error("pattern needs at least {min_len} keys, but codes have at most {max_len}")]
+    TooLong { min_len: usize, max_len: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Key(u8),
+    One,
+    /// Any run of keys, including none. A one-or-more `*` is parsed as `?*`,
+    /// and consecutive stars are merged.
+    Star,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternOptions {
+    /// Whether `*` may match an empty run of keys.
+    pub star_matches_empty: bool,
+}
+
+impl Default for PatternOptions {
+    fn default() -> Self {
+        Self {
+            star_matches_empty: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+    options: PatternOptions,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        Self::parse_with(pattern, PatternOptions::default())
+    }
+
+    pub fn parse_with(pattern: &str, options: PatternOptions) -> Result<Self, PatternError> {
+        if pattern.is_empty() {
+            return Err(PatternError::Empty);
+        }
+        let mut tokens = Vec::new();
+        for (position, character) in pattern.chars().enumerate() {
+            match character {
+                'a'..='z' => tokens.push(Token::Key(character as u8)),
+                '?' => tokens.push(Token::One),
+                '*' => {
+                    if !options.star_matches_empty {
+                        tokens.push(Token::One);
+                    }
+                    if tokens.last() != Some(&Token::Star) {
+                        tokens.push(Token::Star);
+                    }
+                }
+                _ => {
+                    return Err(PatternError::InvalidCharacter {
+                        position,
+                        character,
+                    })
+                }
+            }
+        }
+        let pattern = Self { tokens, options };
+        if pattern.min_len() > CongkitDB::MAX_CODE_LEN {
+            return Err(PatternError::TooLong {
+                min_len: pattern.min_len(),
+                max_len: CongkitDB::MAX_CODE_LEN,
+            });
+        }
+        Ok(pattern)
+    }
+
+    /// The fewest keys a matching code can have.
+    pub fn min_len(&self) -> usize {
+        self.tokens
+            .iter()
+            .filter(|token| **token != Token::Star)
+            .count()
+    }
+
+    /// Whether the pattern has no wildcards, i.e. matches exactly one code.
+    pub fn is_exact(&self) -> bool {
+        self.tokens
+            .iter()
+            .all(|token| matches!(token, Token::Key(_)))
+    }
+
+    /// The keys before the first wildcard.
+    pub(crate) fn literal_prefix(&self) -> CangjieCode {
+        Self::literal_run(self.tokens.iter())
+    }
+
+    /// The keys after the last wildcard.
+    pub(crate) fn literal_suffix(&self) -> CangjieCode {
+        Self::literal_run(self.tokens.iter().rev()).reversed()
+    }
+
+    /// The leading keys of `tokens`, which never outnumber
+    /// [`CangjieCode::MAX_LEN`] in a parsed pattern.
+    fn literal_run<'a>(tokens: impl Iterator<Item = &'a Token>) -> CangjieCode {
+        tokens
+            .map_while(|token| match token {
+                Token::Key(key) => Some(*key as char),
+                _ => None,
+            })
+            .fold(CangjieCode::EMPTY, |code, key| code.push(key).unwrap())
+    }
+
+    pub fn matches(&self, code: &str) -> bool {
+        self.match_keys(code.as_bytes())
+    }
+
+    pub fn matches_code(&self, code: &CangjieCode) -> bool {
+        let mut keys = [0u8; CangjieCode::MAX_LEN];
+        let len = code.len();
+        code.letters()
+            .zip(keys.iter_mut())
+            .for_each(|(key, slot)| *slot = key as u8);
+        self.match_keys(&keys[..len])
+    }
+
+    /// Glob matching that, on a mismatch, only retries the most recent `*`
+    /// with one more key, so it runs in time proportional to the pattern
+    /// length times the code length however many stars there are.
+    fn match_keys(&self, code: &[u8]) -> bool {
+        let (mut token, mut key) = (0, 0);
+        // The token after the last `*` seen, and where its run ends.
+        let mut retry = None;
+        while key < code.len() {
+            match self.tokens.get(token) {
+                Some(Token::Key(k)) if *k == code[key] => (token, key) = (token + 1, key + 1),
+                Some(Token::One) => (token, key) = (token + 1, key + 1),
+                Some(Token::Star) => {
+                    retry = Some((token + 1, key));
+                    token += 1;
+                }
+                _ => match retry {
+                    Some((after_star, run_end)) => {
+                        retry = Some((after_star, run_end + 1));
+                        (token, key) = (after_star, run_end + 1);
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.tokens[token..]
+            .iter()
+            .all(|token| *token == Token::Star)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_or_more(pattern: &str) -> Pattern {
+        let options = PatternOptions {
+            star_matches_empty: false,
+        };
+        Pattern::parse_with(pattern, options).unwrap()
+    }
+
+    #[test]
+    fn star_matches_empty_by_default() {
+        let pattern = Pattern::parse("*hqi").unwrap();
+        assert!(pattern.matches("hqi"));
+        assert!(pattern.matches("ahqi"));
+        assert!(!pattern.matches("hqia"));
+        assert!(Pattern::parse("h*").unwrap().matches("h"));
+    }
+
+    #[test]
+    fn star_matches_one_or_more() {
+        assert!(!one_or_more("*hqi").matches("hqi"));
+        assert!(one_or_more("*hqi").matches("ahqi"));
+        assert!(!one_or_more("h**").matches("ha"));
+        assert!(one_or_more("h**").matches("hab"));
+        assert_eq!(one_or_more("h*").min_len(), 2);
+    }
+
+    #[test]
+    fn question_mark_matches_one_key() {
+        let pattern = Pattern::parse("h?i").unwrap();
+        assert!(pattern.matches("hqi"));
+        assert!(!pattern.matches("hi"));
+        assert!(!pattern.matches("hqqi"));
+    }
+
+    #[test]
+    fn many_stars() {
+        let pattern = Pattern::parse("************z************").unwrap();
+        assert_eq!(pattern, Pattern::parse("*z*").unwrap());
+        assert!(pattern.matches("abzcd"));
+        assert!(!pattern.matches("abcde"));
+        assert!(Pattern::parse("*a*b*c*").unwrap().matches("xaybc"));
+        assert!(!Pattern::parse("*a*b*c*").unwrap().matches("cba"));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert_eq!(Pattern::parse(""), Err(PatternError::Empty));
+        assert_eq!(
+            Pattern::parse("hQ"),
+            Err(PatternError::InvalidCharacter {
+                position: 1,
+                character: 'Q'
+            })
+        );
+        assert_eq!(
+            Pattern::parse("abc?de"),
+            Err(PatternError::TooLong {
+                min_len: 6,
+                max_len: 5
+            })
+        );
+        assert!(Pattern::parse("abcde*").is_ok());
+        assert!(Pattern::parse_with(
+            "abcde*",
+            PatternOptions {
+                star_matches_empty: false
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn literal_runs() {
+        let pattern = Pattern::parse("hq*i").unwrap();
+        assert_eq!(pattern.literal_prefix(), "hq");
+        assert_eq!(pattern.literal_suffix(), "i");
+        assert!(!pattern.is_exact());
+        assert!(Pattern::parse("hqi").unwrap().is_exact());
+    }
+}
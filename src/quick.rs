@@ -0,0 +1,69 @@
+//! Quick (速成) input, where a character is typed with only the first and
+//! last keys of its full Cangjie code.
+
+use crate::{rank, ranked_characters, CangjieCode, CongkitVersion, Entry};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The Quick code for a full code: its first and last keys, or the code
+/// itself when it is a single key.
+pub(crate) fn quick_code(code: CangjieCode) -> CangjieCode {
+    match (code.first(), code.last()) {
+        (Some(first), Some(last)) if code.len() > 1 => CangjieCode::EMPTY
+            .push_key(first)
+            .and_then(|quick| quick.push_key(last))
+            .unwrap_or(code),
+        _ => code,
+    }
+}
+
+/// Sort key for a Quick candidate; see [`QuickIndex`].
+type Rank = (bool, Reverse<i32>, char);
+
+/// Ranked candidates for every Quick code.
+///
+/// Two-key codes collide heavily (hundreds of characters share `hq`), and
+/// `order` is 0 for everything outside Big5, so candidates are ranked:
+///
+/// 1. characters whose full code is the Quick code itself (`hq` → 牛),
+/// 2. Big5 characters in Big5 order, which lists the common level-1
+///    characters before the level-2 ones,
+/// 3. everything else.
+#[derive(Debug, Default)]
+pub(crate) struct QuickIndex {
+    candidates: HashMap<CangjieCode, Vec<char>>,
+}
+
+impl QuickIndex {
+    pub(crate) fn new<'a>(
+        entries: impl Iterator<Item = &'a Entry>,
+        version: CongkitVersion,
+    ) -> Self {
+        let mut ranked: HashMap<CangjieCode, Vec<(Rank, char)>> = HashMap::new();
+        for entry in entries {
+            for code in entry.codes(version) {
+                let quick = quick_code(code);
+                let rank = (
+                    quick.len() != code.len(),
+                    rank(entry.order),
+                    entry.traditional,
+                );
+                ranked
+                    .entry(quick)
+                    .or_default()
+                    .push((rank, entry.traditional));
+            }
+        }
+        let candidates = ranked
+            .into_iter()
+            .map(|(quick, hits)| (quick, ranked_characters(hits)))
+            .collect();
+        Self { candidates }
+    }
+
+    pub(crate) fn get(&self, quick: CangjieCode) -> &[char] {
+        self.candidates
+            .get(&quick)
+            .map_or(&[], |chars| chars.as_slice())
+    }
+}
@@ -0,0 +1,293 @@
+//! A stateful input method engine: key buffer, candidate paging and
+//! selection, independent of any UI.
+
+use crate::{CongkitDB, PrefixCursor};
+
+/// A key event from the front-end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A typed character: `a`–`z` extend the key buffer, and `1`–`9` select
+    /// a candidate on the current page.
+    Char(char),
+    Space,
+    Backspace,
+    Escape,
+    PageUp,
+    PageDown,
+}
+
+/// The session state after a key event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionOutput {
+    /// Whether the session consumed the key. Unhandled keys should be passed
+    /// on to the application.
+    pub handled: bool,
+    /// Text to insert into the application.
+    pub committed: Option<String>,
+    /// The key buffer.
+    pub keys: String,
+    /// The key buffer shown as radicals.
+    pub preedit: String,
+    /// The candidates on the current page; candidate `n` is selected with the
+    /// digit key `n + 1`.
+    pub candidates: Vec<char>,
+    pub page: usize,
+    pub page_count: usize,
+}
+
+/// Turns key events into preedit text, candidate pages and committed text.
+///
+/// Typing `a`–`z` (up to five keys) lists the characters whose code is the
+/// buffer, then those whose code continues it. Space commits the first
+/// candidate on the page, a digit commits the candidate it numbers,
+/// Backspace removes the last key and Escape clears the buffer. Commits are
+/// recorded in the database's [`SelectionHistory`](crate::SelectionHistory)
+/// when learning is turned on.
+#[derive(Debug, Clone)]
+pub struct InputSession<'a> {
+    db: &'a CongkitDB,
+    cursor: PrefixCursor<'a>,
+    candidates: Vec<char>,
+    page: usize,
+    page_size: usize,
+}
+
+impl<'a> InputSession<'a> {
+    pub const DEFAULT_PAGE_SIZE: usize = 9;
+
+    /// Starts an empty session in `db`'s active version.
+    pub fn new(db: &'a CongkitDB) -> Self {
+        Self {
+            db,
+            cursor: db.cursor("").expect("an empty buffer is always valid"),
+            candidates: Vec::new(),
+            page: 0,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Sets the number of candidates per page, between 1 and 9 so that every
+    /// candidate has a digit key.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, 9);
+        self
+    }
+
+    pub fn keys(&self) -> &str {
+        self.cursor.buffer()
+    }
+
+    pub fn preedit(&self) -> String {
+        self.db.get_radicals(self.cursor.buffer())
+    }
+
+    /// The candidates on the current page.
+    pub fn candidates(&self) -> &[char] {
+        let start = self.page * self.page_size;
+        let end = (start + self.page_size).min(self.candidates.len());
+        &self.candidates[start..end]
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.candidates.len().div_ceil(self.page_size)
+    }
+
+    /// Clears the buffer and candidates.
+    pub fn reset(&mut self) {
+        self.cursor.clear();
+        self.refresh();
+    }
+
+    pub fn handle(&mut self, key: Key) -> SessionOutput {
+        let mut committed = None;
+        let handled = match key {
+            Key::Char(c @ 'a'..='z') => {
+                if self.cursor.push(c) {
+                    self.refresh();
+                }
+                true
+            }
+            Key::Char(c @ '1'..='9') if !self.keys().is_empty() => {
+                let n = c as usize - '1' as usize;
+                if let Some(candidate) = self.candidates().get(n).copied() {
+                    committed = Some(self.commit(candidate));
+                }
+                true
+            }
+            Key::Space if !self.keys().is_empty() => {
+                if let Some(candidate) = self.candidates().first().copied() {
+                    committed = Some(self.commit(candidate));
+                }
+                true
+            }
+            Key::Backspace if !self.keys().is_empty() => {
+                self.cursor.pop();
+                self.refresh();
+                true
+            }
+            Key::Escape if !self.keys().is_empty() => {
+                self.reset();
+                true
+            }
+            Key::PageUp if !self.keys().is_empty() => {
+                self.page = self.page.saturating_sub(1);
+                true
+            }
+            Key::PageDown if !self.keys().is_empty() => {
+                if self.page + 1 < self.page_count() {
+                    self.page += 1;
+                }
+                true
+            }
+            _ => false,
+        };
+        SessionOutput {
+            handled,
+            committed,
+            keys: self.keys().to_string(),
+            preedit: self.preedit(),
+            candidates: self.candidates().to_vec(),
+            page: self.page,
+            page_count: self.page_count(),
+        }
+    }
+
+    /// Records the selection with the database's learning, if any, and
+    /// clears the buffer.
+    fn commit(&mut self, candidate: char) -> String {
+        self.db.record_selection(self.keys(), candidate);
+        self.reset();
+        candidate.to_string()
+    }
+
+    fn refresh(&mut self) {
+        self.page = 0;
+        self.candidates = match self.keys().is_empty() {
+            true => Vec::new(),
+            false => {
+                let mut completion = self.cursor.candidates(usize::MAX);
+                self.db.rerank(self.keys(), &mut completion.exact);
+                self.db.rerank(self.keys(), &mut completion.completions);
+                completion.iter().copied().collect()
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CongkitFilter, CongkitVersion};
+
+    const TXT: &str = "日 日 1 1 0 0 1 0 0 0 0 a a NA 100\n\
+                       曰 曰 1 1 0 0 1 0 0 0 0 a a NA 90\n\
+                       昌 昌 1 1 0 0 1 0 0 0 0 aa aa NA 80\n\
+                       明 明 1 1 0 0 1 0 0 0 0 ab ab NA 70\n\
+                       旦 旦 1 1 0 0 1 0 0 0 0 am am NA 60\n";
+
+    fn db() -> CongkitDB {
+        CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap()
+    }
+
+    fn type_keys(session: &mut InputSession, keys: &str) -> SessionOutput {
+        keys.chars()
+            .map(|c| session.handle(Key::Char(c)))
+            .last()
+            .unwrap()
+    }
+
+    #[test]
+    fn pages_through_candidates() {
+        let db = db();
+        let mut session = InputSession::new(&db).with_page_size(2);
+        let output = type_keys(&mut session, "a");
+        assert_eq!(output.candidates, ['日', '曰']);
+        assert_eq!((output.page, output.page_count), (0, 3));
+        assert_eq!(output.preedit, "日");
+
+        assert_eq!(session.handle(Key::PageDown).candidates, ['昌', '明']);
+        assert_eq!(session.handle(Key::PageDown).candidates, ['旦']);
+        let output = session.handle(Key::PageDown);
+        assert_eq!((output.page, output.candidates), (2, vec!['旦']));
+        assert_eq!(session.handle(Key::PageUp).page, 1);
+
+        // Typing another key starts again from the first page.
+        let output = session.handle(Key::Char('b'));
+        assert_eq!((output.page, output.candidates), (0, vec!['明']));
+    }
+
+    #[test]
+    fn selects_with_digits_and_space() {
+        let db = db();
+        let mut session = InputSession::new(&db).with_page_size(2);
+        type_keys(&mut session, "a");
+        session.handle(Key::PageDown);
+        let output = session.handle(Key::Char('2'));
+        assert_eq!(output.committed.as_deref(), Some("明"));
+        assert_eq!(output.keys, "");
+        assert!(output.candidates.is_empty());
+
+        type_keys(&mut session, "a");
+        // No third candidate on a page of two.
+        let output = session.handle(Key::Char('3'));
+        assert!(output.handled);
+        assert_eq!(output.committed, None);
+        assert_eq!(output.keys, "a");
+
+        let output = session.handle(Key::Space);
+        assert_eq!(output.committed.as_deref(), Some("日"));
+        assert_eq!(output.keys, "");
+    }
+
+    #[test]
+    fn backspace_and_escape() {
+        let db = db();
+        let mut session = InputSession::new(&db);
+        assert_eq!(type_keys(&mut session, "ab").candidates, ['明']);
+        let output = session.handle(Key::Backspace);
+        assert_eq!(output.keys, "a");
+        assert_eq!(output.candidates, ['日', '曰', '昌', '明', '旦']);
+
+        let output = session.handle(Key::Escape);
+        assert!(output.handled);
+        assert_eq!(output.keys, "");
+        assert!(output.candidates.is_empty());
+    }
+
+    #[test]
+    fn stops_at_five_keys() {
+        let db = db();
+        let mut session = InputSession::new(&db);
+        type_keys(&mut session, "abcde");
+        let output = session.handle(Key::Char('f'));
+        assert!(output.handled);
+        assert_eq!(output.keys, "abcde");
+        assert_eq!(output.preedit, "日月金木水");
+    }
+
+    #[test]
+    fn passes_keys_through_when_empty() {
+        let db = db();
+        let mut session = InputSession::new(&db);
+        for key in [
+            Key::Char('1'),
+            Key::Space,
+            Key::Backspace,
+            Key::Escape,
+            Key::PageUp,
+            Key::PageDown,
+            Key::Char('A'),
+        ] {
+            let output = session.handle(key);
+            assert!(!output.handled, "{key:?}");
+            assert_eq!(output.committed, None);
+        }
+        type_keys(&mut session, "a");
+        assert!(!session.handle(Key::Char(',')).handled);
+        assert_eq!(session.keys(), "a");
+    }
+}
@@ -0,0 +1,116 @@
+//! Traditional ↔ simplified conversion using the table's simplified column.
+
+use crate::{rank, ranked_characters, unique, CangjieCode, CongkitDB, Entry, PatternError};
+use std::collections::HashMap;
+
+/// Maps each simplified character to its traditional forms.
+///
+/// Several traditional characters can share one simplified form (後 and 后
+/// both simplify to 后), and the table has no frequency data to pick between
+/// them. A simplified character that is also a traditional character in its
+/// own right (in Big5, like 后 or 里) comes first, so that converting text
+/// that is already traditional leaves it alone: 皇后 stays 皇后 rather than
+/// becoming 皇後. Otherwise forms that differ from the simplified character
+/// come first, since one outside Big5 (like 们) needs converting. Within
+/// each group, forms are ranked by `order`, which puts the rarer variants
+/// outside Big5 (whose `order` is 0) last.
+pub(crate) fn traditional_forms<'a>(
+    entries: impl Iterator<Item = &'a Entry>,
+) -> HashMap<char, Vec<char>> {
+    let mut forms: HashMap<char, Vec<&Entry>> = HashMap::new();
+    for entry in entries {
+        if let Some(simplified) = entry.simplified {
+            forms.entry(simplified).or_default().push(entry);
+        }
+    }
+    forms
+        .into_iter()
+        .map(|(simplified, entries)| {
+            let hits = entries
+                .iter()
+                .map(|entry| {
+                    let identity = entry.traditional == simplified;
+                    (
+                        (!(identity && entry.big5), identity, rank(entry.order)),
+                        entry.traditional,
+                    )
+                })
+                .collect();
+            (simplified, ranked_characters(hits))
+        })
+        .collect()
+}
+
+impl CongkitDB {
+    /// Returns the simplified form of `character`, if the table has one.
+    pub fn get_simplified(&self, character: &char) -> Option<char> {
+        self.entries
+            .get(character)?
+            .iter()
+            .find_map(|entry| entry.simplified)
+    }
+
+    /// Returns every traditional form of the simplified `character`, most
+    /// likely first.
+    pub fn get_traditional(&self, character: &char) -> Vec<char> {
+        self.traditional.get(character).cloned().unwrap_or_default()
+    }
+
+    /// Converts `text` to simplified characters, leaving characters without
+    /// a simplified form unchanged.
+    pub fn to_simplified(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| self.get_simplified(&c).unwrap_or(c))
+            .collect()
+    }
+
+    /// Converts `text` to traditional characters, taking the first of
+    /// [`get_traditional`](Self::get_traditional) where there are several.
+    pub fn to_traditional(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| match self.traditional.get(&c) {
+                Some(forms) => forms[0],
+                None => c,
+            })
+            .collect()
+    }
+
+    /// Like [`get_characters`](Self::get_characters), but returns the
+    /// simplified form of each match. Characters that simplify to the same
+    /// form are merged, keeping the first position.
+    pub fn get_simplified_characters(&self, pattern: &str) -> Result<Vec<char>, PatternError> {
+        Ok(unique(
+            self.get_characters(pattern)?
+                .into_iter()
+                .map(|c| self.get_simplified(&c).unwrap_or(c)),
+        ))
+    }
+
+    /// Returns the codes of the simplified `character` by way of its
+    /// traditional forms, in the order of
+    /// [`get_traditional`](Self::get_traditional).
+    pub fn get_code_simplified(&self, character: &char) -> Option<Vec<CangjieCode>> {
+        Some(unique(self.traditional.get(character)?.iter().flat_map(
+            |traditional| self.get_code(traditional).unwrap_or_default(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CongkitDB, CongkitFilter, CongkitVersion};
+
+    const TXT: &str = "后 后 1 1 0 0 1 0 0 0 0 hmr hmr NA 22692\n\
+                       後 后 1 1 0 0 1 0 0 0 0 hovie hovie NA 21277\n\
+                       们 们 1 0 0 0 1 0 0 0 0 ols olis NA 0\n\
+                       們 们 1 1 0 0 1 0 0 0 0 oan oan NA 20786\n";
+
+    #[test]
+    fn keeps_traditional_text_unchanged() {
+        let db = CongkitDB::from_txt(TXT, CongkitVersion::V3, CongkitFilter::all()).unwrap();
+        assert_eq!(db.get_traditional(&'后'), ['后', '後']);
+        assert_eq!(db.get_traditional(&'们'), ['們', '们']);
+        assert_eq!(db.to_traditional("皇后们"), "皇后們");
+        assert_eq!(db.to_simplified("後們"), "后们");
+    }
+}